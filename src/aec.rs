@@ -0,0 +1,244 @@
+// Acoustic echo cancellation for the mic track, using the loopback (system audio)
+// track as far-end reference. The bulk delay between the two captures is estimated
+// first, then a partitioned block frequency-domain NLMS filter models the room
+// echo path and subtracts the estimated echo from the mic.
+
+use std::collections::VecDeque;
+
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+
+// Longest speaker-to-mic delay searched for (output latency + acoustic path)
+const MAX_BULK_DELAY_MS: u32 = 500;
+// Echo tail modelled by the adaptive filter once the bulk delay is removed
+const ECHO_TAIL_MS: u32 = 150;
+// Amount of audio used for delay estimation
+const DELAY_ANALYSIS_SECS: u32 = 30;
+
+const STEP_SIZE: f32 = 0.5;
+const POWER_SMOOTHING: f32 = 0.9;
+const REGULARIZATION: f32 = 1e-6;
+
+/// Estimate how many samples the echo in `mic` lags behind `reference` (GCC-PHAT).
+/// Returns None when the reference is silent over the analysis window.
+pub fn estimate_bulk_delay(mic: &[f32], reference: &[f32], sample_rate: u32) -> Option<usize> {
+    let max_delay = (sample_rate * MAX_BULK_DELAY_MS / 1000) as usize;
+    let window = ((sample_rate * DELAY_ANALYSIS_SECS) as usize)
+        .min(mic.len())
+        .min(reference.len());
+    if window <= max_delay {
+        return None;
+    }
+
+    let reference_energy: f32 = reference[..window].iter().map(|s| s * s).sum();
+    if reference_energy / (window as f32) < 1e-8 {
+        return None;
+    }
+
+    let fft_size = (window + max_delay).next_power_of_two();
+    let mut planner = FftPlanner::<f32>::new();
+    let forward = planner.plan_fft_forward(fft_size);
+    let inverse = planner.plan_fft_inverse(fft_size);
+
+    let mut mic_spectrum: Vec<Complex<f32>> = (0..fft_size)
+        .map(|i| Complex::new(if i < window { mic[i] } else { 0.0 }, 0.0))
+        .collect();
+    let mut reference_spectrum: Vec<Complex<f32>> = (0..fft_size)
+        .map(|i| Complex::new(if i < window { reference[i] } else { 0.0 }, 0.0))
+        .collect();
+    forward.process(&mut mic_spectrum);
+    forward.process(&mut reference_spectrum);
+
+    // Phase transform: keep only the phase of the cross spectrum
+    let mut cross: Vec<Complex<f32>> = mic_spectrum
+        .iter()
+        .zip(&reference_spectrum)
+        .map(|(m, r)| {
+            let product = m * r.conj();
+            let magnitude = product.norm();
+            if magnitude > 1e-12 { product / magnitude } else { Complex::new(0.0, 0.0) }
+        })
+        .collect();
+    inverse.process(&mut cross);
+
+    cross[..=max_delay]
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1.re.total_cmp(&b.1.re))
+        .map(|(lag, _)| lag)
+}
+
+/// Remove the echo of `reference` from `mic`. Both tracks are mono at `sample_rate`
+/// and start at (roughly) the same instant. The output has the length of `mic`.
+pub fn cancel_echo(mic: &[f32], reference: &[f32], sample_rate: u32) -> Vec<f32> {
+    let Some(delay) = estimate_bulk_delay(mic, reference, sample_rate) else {
+        println!("AEC: no far-end signal, mic track left untouched");
+        return mic.to_vec();
+    };
+    println!("AEC: estimated echo delay {} ms", delay as u64 * 1000 / sample_rate as u64);
+
+    let block = ((sample_rate / 100) as usize).next_power_of_two();
+    let fft_size = 2 * block;
+    let bins = fft_size;
+    let partitions = ((sample_rate * ECHO_TAIL_MS / 1000) as usize).div_ceil(block).max(1);
+
+    let mut planner = FftPlanner::<f32>::new();
+    let forward = planner.plan_fft_forward(fft_size);
+    let inverse = planner.plan_fft_inverse(fft_size);
+    let scale = 1.0 / fft_size as f32;
+
+    // Reference shifted by the bulk delay, so the filter only has to model the tail
+    let delayed_reference = |i: usize| -> f32 {
+        if i >= delay { reference.get(i - delay).copied().unwrap_or(0.0) } else { 0.0 }
+    };
+
+    let zero = Complex::new(0.0f32, 0.0);
+    let mut weights = vec![vec![zero; bins]; partitions];
+    let mut history: VecDeque<Vec<Complex<f32>>> = (0..partitions).map(|_| vec![zero; bins]).collect();
+    let mut reference_power = vec![0.0f32; bins];
+    let mut error_power = vec![0.0f32; bins];
+    let mut previous_block = vec![0.0f32; block];
+    let mut output = Vec::with_capacity(mic.len());
+    let mut buffer = vec![zero; fft_size];
+
+    let mut start = 0;
+    let mut block_index = 0;
+    while start < mic.len() {
+        // Far-end spectrum of [previous block, current block] (overlap-save)
+        let current_block: Vec<f32> = (start..start + block).map(delayed_reference).collect();
+        let mut spectrum: Vec<Complex<f32>> = previous_block
+            .iter()
+            .chain(&current_block)
+            .map(|&s| Complex::new(s, 0.0))
+            .collect();
+        forward.process(&mut spectrum);
+        previous_block = current_block;
+
+        for (power, bin) in reference_power.iter_mut().zip(&spectrum) {
+            *power = POWER_SMOOTHING * *power + (1.0 - POWER_SMOOTHING) * bin.norm_sqr();
+        }
+        history.pop_back();
+        history.push_front(spectrum);
+
+        // Echo estimate: sum of every partition filtered by its weights
+        buffer.iter_mut().for_each(|b| *b = zero);
+        for (partition_weights, partition_spectrum) in weights.iter().zip(&history) {
+            for ((acc, w), x) in buffer.iter_mut().zip(partition_weights).zip(partition_spectrum) {
+                *acc += w * x;
+            }
+        }
+        inverse.process(&mut buffer);
+
+        // Error = mic - echo estimate (last half of the overlap-save output)
+        let mut error_block = vec![0.0f32; block];
+        for (i, error) in error_block.iter_mut().enumerate() {
+            let mic_sample = mic.get(start + i).copied().unwrap_or(0.0);
+            *error = mic_sample - buffer[block + i].re * scale;
+        }
+        let remaining = mic.len() - start;
+        output.extend_from_slice(&error_block[..block.min(remaining)]);
+
+        let mut error_spectrum = vec![zero; fft_size];
+        for (slot, &error) in error_spectrum[block..].iter_mut().zip(&error_block) {
+            *slot = Complex::new(error, 0.0);
+        }
+        forward.process(&mut error_spectrum);
+        for (power, bin) in error_power.iter_mut().zip(&error_spectrum) {
+            *power = POWER_SMOOTHING * *power + (1.0 - POWER_SMOOTHING) * bin.norm_sqr();
+        }
+
+        // NLMS update, with the step shared between partitions. Normalizing by the error
+        // power too slows adaptation down during double talk, when the near-end speaker
+        // dominates the error.
+        let step = STEP_SIZE / partitions as f32;
+        for (partition_weights, partition_spectrum) in weights.iter_mut().zip(&history) {
+            let bins_iter = partition_weights
+                .iter_mut()
+                .zip(partition_spectrum)
+                .zip(&error_spectrum)
+                .zip(reference_power.iter().zip(&error_power));
+            for (((w, x), e), (x_power, e_power)) in bins_iter {
+                *w += x.conj() * e * (step / (x_power + e_power + REGULARIZATION));
+            }
+        }
+
+        // Gradient constraint on one partition per block (keeps the filter causal)
+        let constrained = block_index % partitions;
+        buffer.copy_from_slice(&weights[constrained]);
+        inverse.process(&mut buffer);
+        for value in buffer.iter_mut().skip(block) {
+            *value = zero;
+        }
+        for value in buffer.iter_mut().take(block) {
+            *value *= scale;
+        }
+        forward.process(&mut buffer);
+        weights[constrained].copy_from_slice(&buffer);
+
+        start += block;
+        block_index += 1;
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 16000;
+
+    /// Deterministic white noise standing in for far-end speech
+    fn noise(len: usize) -> Vec<f32> {
+        let mut seed = 0x9e37_79b9_u32;
+        (0..len)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                seed as f32 / u32::MAX as f32 - 0.5
+            })
+            .collect()
+    }
+
+    /// The reference played through a room: a delayed, attenuated copy and a weaker reflection
+    fn echo_of(reference: &[f32], delay: usize) -> Vec<f32> {
+        let reflection = delay + 37;
+        (0..reference.len())
+            .map(|i| {
+                let direct = if i >= delay { 0.5 * reference[i - delay] } else { 0.0 };
+                let reflected = if i >= reflection { 0.2 * reference[i - reflection] } else { 0.0 };
+                direct + reflected
+            })
+            .collect()
+    }
+
+    fn power_db(samples: &[f32]) -> f32 {
+        10.0 * (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).max(1e-20).log10()
+    }
+
+    #[test]
+    fn estimates_delay_and_cancels_synthetic_echo() {
+        let delay = (RATE * 120 / 1000) as usize;
+        let reference = noise(RATE as usize * 10);
+        let mic = echo_of(&reference, delay);
+
+        let estimated = estimate_bulk_delay(&mic, &reference, RATE).unwrap();
+        assert!(estimated.abs_diff(delay) <= 1, "estimated {} samples, expected {}", estimated, delay);
+
+        let output = cancel_echo(&mic, &reference, RATE);
+        assert_eq!(output.len(), mic.len());
+        // Echo return loss enhancement over the last 3 s, once the filter has converged
+        let converged = mic.len() - RATE as usize * 3;
+        let erle = power_db(&mic[converged..]) - power_db(&output[converged..]);
+        assert!(erle > 20.0, "ERLE {} dB", erle);
+    }
+
+    #[test]
+    fn silent_reference_leaves_mic_untouched() {
+        let mic = noise(RATE as usize * 2);
+        let reference = vec![0.0; mic.len()];
+        assert_eq!(estimate_bulk_delay(&mic, &reference, RATE), None);
+        assert_eq!(cancel_echo(&mic, &reference, RATE), mic);
+    }
+}
@@ -0,0 +1,164 @@
+// Automatic gain control for the microphone path.
+// Works on whole capture blocks: the block RMS is compared to the target level and the
+// gain (in dB) moves towards the required value with separate attack/release times.
+
+// Below this level the block is treated as silence and the gain is held,
+// so the AGC does not pump up background noise between sentences
+const SILENCE_THRESHOLD_DBFS: f32 = -55.0;
+const MIN_GAIN_DB: f32 = -20.0;
+
+pub struct AutoGain {
+    pub enabled: bool,
+    pub target_dbfs: f32,
+    pub attack_ms: f32,
+    pub release_ms: f32,
+    pub max_gain_db: f32,
+    samples_per_second: f32,
+    gain_db: f32,
+}
+
+fn to_db(linear: f32) -> f32 {
+    20.0 * linear.max(1e-9).log10()
+}
+
+fn from_db(db: f32) -> f32 {
+    10_f32.powf(db / 20.0)
+}
+
+impl AutoGain {
+    pub fn new(enabled: bool, target_dbfs: f32, attack_ms: f32, release_ms: f32, max_gain_db: f32) -> Self {
+        Self {
+            enabled,
+            target_dbfs,
+            attack_ms,
+            release_ms,
+            max_gain_db,
+            samples_per_second: 48000.0,
+            gain_db: 0.0,
+        }
+    }
+
+    /// Restart adaptation from `initial_gain` (usually the fixed mic gain).
+    /// `samples_per_second` counts interleaved samples (rate * channels).
+    pub fn reset(&mut self, samples_per_second: u32, initial_gain: f32) {
+        self.samples_per_second = samples_per_second.max(1) as f32;
+        self.gain_db = to_db(initial_gain);
+    }
+
+    /// Gain currently applied to the microphone (linear)
+    pub fn effective_gain(&self) -> f32 {
+        from_db(self.gain_db)
+    }
+
+    /// Update the gain from one block of raw (un-gained) samples and return the new gain.
+    /// When disabled, the fixed gain is passed through unchanged.
+    pub fn gain_for_block(&mut self, block: &[f32], fixed_gain: f32) -> f32 {
+        if !self.enabled {
+            self.gain_db = to_db(fixed_gain);
+            return fixed_gain;
+        }
+        if block.is_empty() {
+            return self.effective_gain();
+        }
+
+        let sum: f32 = block.iter().map(|&s| s * s).sum();
+        let rms_dbfs = to_db((sum / block.len() as f32).sqrt());
+        if rms_dbfs < SILENCE_THRESHOLD_DBFS {
+            return self.effective_gain();
+        }
+
+        let desired_db = (self.target_dbfs - rms_dbfs).clamp(MIN_GAIN_DB, self.max_gain_db);
+        let time_constant_ms = if desired_db < self.gain_db {
+            self.attack_ms
+        } else {
+            self.release_ms
+        };
+
+        let block_ms = block.len() as f32 * 1000.0 / self.samples_per_second;
+        let coeff = 1.0 - (-block_ms / time_constant_ms.max(1.0)).exp();
+        self.gain_db += (desired_db - self.gain_db) * coeff;
+
+        self.effective_gain()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 48000;
+    // 10 ms blocks, a whole number of periods of the 1 kHz test tone
+    const BLOCK: usize = 480;
+    const ATTACK_MS: f32 = 50.0;
+    const RELEASE_MS: f32 = 500.0;
+    const MAX_GAIN_DB: f32 = 15.0;
+
+    /// One block of a 1 kHz sine with the given RMS level
+    fn block(level_dbfs: f32) -> Vec<f32> {
+        let amplitude = from_db(level_dbfs) * std::f32::consts::SQRT_2;
+        (0..BLOCK)
+            .map(|i| amplitude * (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / RATE as f32).sin())
+            .collect()
+    }
+
+    /// Gain in dB after each block of `duration_ms` at `level_dbfs`
+    fn feed(agc: &mut AutoGain, level_dbfs: f32, duration_ms: usize) -> Vec<f32> {
+        let block = block(level_dbfs);
+        (0..duration_ms / 10)
+            .map(|_| to_db(agc.gain_for_block(&block, 1.0)))
+            .collect()
+    }
+
+    /// Time for the gain to cover 1 - 1/e of the way from `from_db` to `to_db`
+    fn time_constant_ms(gains: &[f32], from_db: f32, to_db: f32) -> f32 {
+        let threshold = from_db + (to_db - from_db) * (1.0 - (-1.0f32).exp());
+        let blocks = gains
+            .iter()
+            .position(|&gain| (gain - threshold) * (to_db - from_db).signum() >= 0.0)
+            .expect("gain never reached the threshold");
+        (blocks + 1) as f32 * 10.0
+    }
+
+    #[test]
+    fn follows_level_steps_with_attack_and_release_times() {
+        let mut agc = AutoGain::new(true, -20.0, ATTACK_MS, RELEASE_MS, MAX_GAIN_DB);
+        agc.reset(RATE, 1.0);
+
+        // Quiet: needs +20 dB, capped at the max gain
+        let quiet = feed(&mut agc, -40.0, 4000);
+        assert!(quiet.iter().all(|&gain| gain <= MAX_GAIN_DB + 1e-3));
+        assert!((quiet.last().unwrap() - MAX_GAIN_DB).abs() < 0.1);
+
+        // Loud: the gain drops to -14 dB with the attack time
+        let loud = feed(&mut agc, -6.0, 1000);
+        let attack = time_constant_ms(&loud, MAX_GAIN_DB, -14.0);
+        assert!((attack - ATTACK_MS).abs() <= 10.0, "attack took {} ms", attack);
+        assert!((loud.last().unwrap() + 14.0).abs() < 0.1);
+
+        // Quiet again: the gain rises back with the release time
+        let quiet = feed(&mut agc, -40.0, 4000);
+        let release = time_constant_ms(&quiet, -14.0, MAX_GAIN_DB);
+        assert!((release - RELEASE_MS).abs() <= 20.0, "release took {} ms", release);
+        assert!(quiet.iter().all(|&gain| gain <= MAX_GAIN_DB + 1e-3));
+    }
+
+    #[test]
+    fn holds_gain_during_silence() {
+        let mut agc = AutoGain::new(true, -20.0, ATTACK_MS, RELEASE_MS, MAX_GAIN_DB);
+        agc.reset(RATE, 1.0);
+        let held = *feed(&mut agc, -6.0, 1000).last().unwrap();
+
+        // Background noise below the gate must not pump the gain up
+        let silence = feed(&mut agc, -70.0, 3000);
+        assert!(silence.iter().all(|&gain| (gain - held).abs() < 1e-4));
+        assert!((to_db(agc.gain_for_block(&[0.0; BLOCK], 1.0)) - held).abs() < 1e-4);
+    }
+
+    #[test]
+    fn disabled_passes_the_fixed_gain_through() {
+        let mut agc = AutoGain::new(false, -20.0, ATTACK_MS, RELEASE_MS, MAX_GAIN_DB);
+        agc.reset(RATE, 1.0);
+        assert_eq!(agc.gain_for_block(&block(-40.0), 2.0), 2.0);
+        assert!((agc.effective_gain() - 2.0).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,317 @@
+// Command-line interface for headless and scripted use (cron, SSH sessions).
+// Subcommands reuse the recorder, encoder and uploader of the GUI without opening a display.
+
+use std::time::Duration;
+
+use gtk4::glib;
+
+use crate::upload_history::{self, UploadRecord};
+use crate::upload_request::UploadResponse;
+use crate::{find_destination, migrate_url_secrets, upload_recording, Config, RecorderState, SpeakerCounts, UploadDestination};
+
+const USAGE: &str = "Usage:
+  audio-recorder                      Launch the GUI (or show the window of the running one)
+  audio-recorder --toggle             Start or stop recording in the running instance
+  audio-recorder --start | --stop     Start / stop recording in the running instance
+  audio-recorder --show               Show the window of the running instance
+  audio-recorder record [options]     Record without a window
+      --duration <time>               Stop after e.g. 90s, 30m, 1h30m (default: until Ctrl-C)
+      --mic <name>                    Microphone source (name or description, 'none' to disable)
+      --loopback <name>               System audio monitor source ('none' to disable)
+      --out <file.ogg>                Output file (default: save directory from settings)
+      --upload                        Upload once saved, to the default destination
+      --destination <name>            Upload destination (implies --upload)
+      --min-speakers <n>              Speaker count hints (default: the destination's)
+      --max-speakers <n>
+  audio-recorder list-sources         List microphones and monitor sources
+  audio-recorder upload <file> [--destination <name> | --endpoint <url>] [--min-speakers <n>] [--max-speakers <n>]
+  audio-recorder help                 Show this help";
+
+/// Run a subcommand if one was given. Returns None to fall through to the GUI.
+pub fn run(args: &[String]) -> Option<glib::ExitCode> {
+    let command = args.get(1)?;
+    let rest = &args[2..];
+
+    let result = match command.as_str() {
+        "record" => record(rest),
+        "list-sources" => {
+            list_sources();
+            Ok(())
+        }
+        "upload" => upload(rest),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        // Anything else (e.g. GTK options) is left to the GUI
+        _ => return None,
+    };
+
+    Some(match result {
+        Ok(()) => glib::ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("Error: {}", message);
+            glib::ExitCode::FAILURE
+        }
+    })
+}
+
+/// Parse durations such as "45", "90s", "30m", "1h30m"
+fn parse_duration(text: &str) -> Result<Duration, String> {
+    let mut total = 0u64;
+    let mut number = String::new();
+    for c in text.trim().chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let value: u64 = number.parse().map_err(|_| format!("invalid duration: {}", text))?;
+        total += match c {
+            'h' => value * 3600,
+            'm' => value * 60,
+            's' => value,
+            _ => return Err(format!("invalid duration unit '{}' in {}", c, text)),
+        };
+        number.clear();
+    }
+    if !number.is_empty() {
+        total += number.parse::<u64>().map_err(|_| format!("invalid duration: {}", text))?;
+    }
+    if total == 0 {
+        return Err(format!("invalid duration: {}", text));
+    }
+    Ok(Duration::from_secs(total))
+}
+
+/// Simple `--flag value` / `--switch` option parser
+struct Options {
+    values: Vec<(String, Option<String>)>,
+    positional: Vec<String>,
+}
+
+impl Options {
+    fn parse(args: &[String], switches: &[&str]) -> Result<Self, String> {
+        let mut values = Vec::new();
+        let mut positional = Vec::new();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            if let Some(name) = arg.strip_prefix("--") {
+                if switches.contains(&name) {
+                    values.push((name.to_string(), None));
+                } else {
+                    let value = iter.next().ok_or_else(|| format!("missing value for --{}", name))?;
+                    values.push((name.to_string(), Some(value.clone())));
+                }
+            } else {
+                positional.push(arg.clone());
+            }
+        }
+        Ok(Self { values, positional })
+    }
+
+    fn value(&self, name: &str) -> Option<&str> {
+        self.values
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .and_then(|(_, v)| v.as_deref())
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.values.iter().any(|(n, _)| n == name)
+    }
+
+    fn number(&self, name: &str, default: u32) -> Result<u32, String> {
+        match self.value(name) {
+            Some(v) => v.parse().map_err(|_| format!("invalid number for --{}: {}", name, v)),
+            None => Ok(default),
+        }
+    }
+
+    /// --min-speakers and --max-speakers, the destination's defaults when not given
+    fn speakers(&self, defaults: SpeakerCounts) -> Result<(u32, u32), String> {
+        let min_speakers = self.number("min-speakers", defaults.min)?;
+        let max_speakers = self.number("max-speakers", defaults.max)?.max(min_speakers);
+        Ok((min_speakers, max_speakers))
+    }
+
+    fn check_known(&self, known: &[&str]) -> Result<(), String> {
+        match self.values.iter().find(|(n, _)| !known.contains(&n.as_str())) {
+            Some((name, _)) => Err(format!("unknown option --{}\n\n{}", name, USAGE)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Find a source by exact name, then by case-insensitive match on name or description
+fn find_source(state: &RecorderState, query: &str, monitor: bool) -> Result<usize, String> {
+    let candidates = || {
+        state
+            .available_sources
+            .iter()
+            .enumerate()
+            .filter(move |(_, s)| s.is_monitor == monitor)
+    };
+    let query_lower = query.to_lowercase();
+    candidates()
+        .find(|(_, s)| s.name == query)
+        .or_else(|| {
+            candidates().find(|(_, s)| {
+                s.name.to_lowercase().contains(&query_lower)
+                    || s.display_name.to_lowercase().contains(&query_lower)
+            })
+        })
+        .map(|(idx, _)| idx)
+        .ok_or_else(|| format!("no {} source matching '{}' (see list-sources)", if monitor { "monitor" } else { "microphone" }, query))
+}
+
+fn list_sources() {
+    let sources = RecorderState::get_available_sources();
+    if sources.is_empty() {
+        eprintln!("No sources found (is pactl available?)");
+        return;
+    }
+    for (kind, monitor) in [("Microphones", false), ("System audio (monitors)", true)] {
+        println!("{}:", kind);
+        for source in sources.iter().filter(|s| s.is_monitor == monitor) {
+            println!("  {}\n      {}", source.name, source.display_name);
+        }
+    }
+}
+
+fn record(args: &[String]) -> Result<(), String> {
+    let options = Options::parse(args, &["upload"])?;
+    options.check_known(&["duration", "mic", "loopback", "out", "upload", "destination", "min-speakers", "max-speakers"])?;
+    let duration = options.value("duration").map(parse_duration).transpose()?;
+
+    let mut state = RecorderState::new();
+
+    if let Some(mic) = options.value("mic") {
+        state.selected_mic_index = if mic == "none" {
+            None
+        } else {
+            Some(find_source(&state, mic, false)?)
+        };
+    }
+    if let Some(loopback) = options.value("loopback") {
+        state.selected_loopback_index = if loopback == "none" {
+            None
+        } else {
+            Some(find_source(&state, loopback, true)?)
+        };
+    }
+    if let Some(out) = options.value("out") {
+        state.output_path = Some(out.to_string());
+    }
+
+    // The upload dialog needs a window: uploads are done here instead, on request
+    let destination = if options.flag("upload") || options.value("destination").is_some() {
+        let destinations = state.upload_destinations.lock().unwrap().clone();
+        let default_destination = state.default_destination.lock().unwrap().clone();
+        Some(configured_destination(&destinations, default_destination.as_deref(), options.value("destination"))?)
+    } else {
+        None
+    };
+    let (min_speakers, max_speakers) =
+        options.speakers(destination.as_ref().map(|d| d.default_speakers).unwrap_or_default())?;
+    *state.n8n_enabled.lock().unwrap() = false;
+
+    state.start_recording();
+    if state.input_stream.is_none() && state.output_stream.is_none() {
+        return Err("no audio stream could be started".to_string());
+    }
+
+    match duration {
+        Some(d) => eprintln!("Recording for {} s (Ctrl-C to stop early)...", d.as_secs()),
+        None => eprintln!("Recording... press Ctrl-C to stop"),
+    }
+
+    // Headless main loop: stops on timeout or SIGINT/SIGTERM
+    let main_loop = glib::MainLoop::new(None, false);
+    for signal in [libc::SIGINT, libc::SIGTERM] {
+        let main_loop = main_loop.clone();
+        glib::unix_signal_add_local(signal, move || {
+            main_loop.quit();
+            glib::ControlFlow::Break
+        });
+    }
+    if let Some(d) = duration {
+        let main_loop = main_loop.clone();
+        glib::timeout_add_local_once(d, move || main_loop.quit());
+    }
+    main_loop.run();
+
+    eprintln!("Stopping, encoding...");
+    let encode_thread = state.stop_recording();
+    let path = encode_thread
+        .and_then(|handle| handle.join().ok().flatten())
+        .ok_or("recording could not be saved (no audio captured?)")?;
+    println!("{}", path);
+
+    if let Some(destination) = destination {
+        let response = upload_recording(&path, &destination, min_speakers, max_speakers).map_err(|e| e.to_string())?;
+        report_upload(&path, &destination, response);
+    }
+
+    Ok(())
+}
+
+fn upload(args: &[String]) -> Result<(), String> {
+    let options = Options::parse(args, &[])?;
+    options.check_known(&["destination", "endpoint", "min-speakers", "max-speakers"])?;
+    let file = options.positional.first().ok_or_else(|| USAGE.to_string())?;
+
+    // --endpoint sends to a bare URL, without authentication or extra fields
+    let destination = match options.value("endpoint") {
+        Some(url) => UploadDestination {
+            name: url.to_string(),
+            url: url.to_string(),
+            ..Default::default()
+        },
+        None => {
+            let config = Config::load().ok_or("no configuration found (use --endpoint)")?;
+            // The app moves them for good on its next start; until then this upload needs them
+            if !config.secrets_by_destination {
+                if let Err(e) = migrate_url_secrets(&config.upload_destinations) {
+                    eprintln!("Warning: could not move the upload secrets to their destinations: {}", e);
+                }
+            }
+            configured_destination(
+                &config.upload_destinations,
+                config.default_destination.as_deref(),
+                options.value("destination"),
+            )?
+        }
+    };
+    let (min_speakers, max_speakers) = options.speakers(destination.default_speakers)?;
+
+    let response = upload_recording(file, &destination, min_speakers, max_speakers).map_err(|e| e.to_string())?;
+    report_upload(file, &destination, response);
+    Ok(())
+}
+
+/// Print what the destination answered and keep it in the upload history
+fn report_upload(file: &str, destination: &UploadDestination, response: UploadResponse) {
+    eprintln!("Uploaded {} to {}", file, destination.name);
+    if let Some(url) = &response.url {
+        eprintln!("URL: {}", url);
+    }
+    if let Some(id) = &response.id {
+        eprintln!("ID: {}", id);
+    }
+    upload_history::record(&UploadRecord::new(file, &destination.name, response), true);
+}
+
+/// Destination named on the command line, or the default one
+fn configured_destination(
+    destinations: &[UploadDestination],
+    default_destination: Option<&str>,
+    name: Option<&str>,
+) -> Result<UploadDestination, String> {
+    find_destination(destinations, default_destination, name)
+        .cloned()
+        .ok_or_else(|| match name {
+            Some(name) => format!("no upload destination named '{}'", name),
+            None => "no upload destination configured".to_string(),
+        })
+}
@@ -0,0 +1,127 @@
+// Session D-Bus control interface, so scripts, desktop shortcuts and calendar tools
+// can drive the running recorder. Method calls send the same UI messages as the tray
+// menu; state changes come back through `emit_state_changed`.
+//
+//   gdbus call --session --dest com.audio.recorder.Control \
+//       --object-path /com/audio/recorder/Control --method com.audio.recorder.Control.Start
+
+use std::sync::Mutex;
+
+use gtk4::gio;
+use gtk4::glib;
+use gtk4::glib::prelude::*;
+
+use crate::{send_ui_message, UiMessage, RECORDING_STATE};
+
+pub const BUS_NAME: &str = "com.audio.recorder.Control";
+const OBJECT_PATH: &str = "/com/audio/recorder/Control";
+const INTERFACE: &str = "com.audio.recorder.Control";
+
+const INTROSPECTION_XML: &str = r#"
+<node>
+  <interface name="com.audio.recorder.Control">
+    <method name="Start"/>
+    <method name="Stop"/>
+    <!-- Toggles between paused and recording, like the tray menu -->
+    <method name="Pause"/>
+    <method name="Status">
+      <arg name="recording" type="b" direction="out"/>
+      <arg name="paused" type="b" direction="out"/>
+      <arg name="elapsed_seconds" type="t" direction="out"/>
+    </method>
+    <signal name="StateChanged">
+      <arg name="recording" type="b"/>
+      <arg name="paused" type="b"/>
+    </signal>
+  </interface>
+</node>
+"#;
+
+// Connection the service is registered on, used to emit StateChanged
+static CONNECTION: Mutex<Option<gio::DBusConnection>> = Mutex::new(None);
+
+/// Own the bus name and export the control object. Callbacks run on the main loop.
+pub fn start() {
+    gio::bus_own_name(
+        gio::BusType::Session,
+        BUS_NAME,
+        gio::BusNameOwnerFlags::NONE,
+        |connection, _| {
+            if let Err(e) = register_object(&connection) {
+                eprintln!("Failed to register D-Bus control object: {}", e);
+                return;
+            }
+            *CONNECTION.lock().unwrap() = Some(connection);
+        },
+        |_, name| println!("D-Bus control interface available as {}", name),
+        |_, name| {
+            eprintln!("Could not own D-Bus name {} (another instance running?)", name);
+            *CONNECTION.lock().unwrap() = None;
+        },
+    );
+}
+
+fn register_object(connection: &gio::DBusConnection) -> Result<(), glib::Error> {
+    let node = gio::DBusNodeInfo::for_xml(INTROSPECTION_XML)?;
+    let interface = node
+        .lookup_interface(INTERFACE)
+        .expect("interface is declared in the introspection XML");
+
+    connection
+        .register_object(OBJECT_PATH, &interface)
+        .method_call(|_, _, _, _, method, _, invocation| handle_method_call(method, invocation))
+        .build()?;
+    Ok(())
+}
+
+fn handle_method_call(method: &str, invocation: gio::DBusMethodInvocation) {
+    let Some(state_ref) = RECORDING_STATE.lock().unwrap().clone() else {
+        invocation.return_dbus_error("org.freedesktop.DBus.Error.Failed", "Recorder not ready");
+        return;
+    };
+    let state = state_ref.lock().unwrap().clone();
+
+    let result = match method {
+        "Start" if state.recording => Err("Already recording"),
+        "Start" => Ok(Some(UiMessage::StartRecording)),
+        "Stop" | "Pause" if !state.recording => Err("Not recording"),
+        "Stop" => Ok(Some(UiMessage::StopRecording)),
+        "Pause" => Ok(Some(UiMessage::TogglePause)),
+        "Status" => Ok(None),
+        _ => {
+            invocation.return_dbus_error(
+                "org.freedesktop.DBus.Error.UnknownMethod",
+                &format!("Unknown method {}", method),
+            );
+            return;
+        }
+    };
+
+    match result {
+        Ok(Some(message)) => {
+            println!("{} requested over D-Bus", method);
+            send_ui_message(message);
+            invocation.return_value(None);
+        }
+        Ok(None) => {
+            let status = (state.recording, state.paused, state.elapsed_secs).to_variant();
+            invocation.return_value(Some(&status));
+        }
+        Err(message) => invocation.return_dbus_error("com.audio.recorder.Control.Error.InvalidState", message),
+    }
+}
+
+/// Broadcast a StateChanged signal (no-op until the bus name is owned)
+pub fn emit_state_changed(recording: bool, paused: bool) {
+    if let Some(connection) = CONNECTION.lock().unwrap().as_ref() {
+        if let Err(e) = connection.emit_signal(
+            None,
+            OBJECT_PATH,
+            INTERFACE,
+            "StateChanged",
+            Some(&(recording, paused).to_variant()),
+        ) {
+            eprintln!("Failed to emit StateChanged: {}", e);
+        }
+    }
+}
@@ -0,0 +1,275 @@
+// Clean-up chain for the microphone track: high-pass filter, spectral noise
+// suppression and noise gate. Runs on the mono mic track before it is mixed
+// with system audio.
+
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+
+// Spectral suppressor tuning
+const OVER_SUBTRACTION: f32 = 2.0;
+const POWER_SMOOTHING: f32 = 0.8;
+const GAIN_SMOOTHING: f32 = 0.6;
+// Bins below this multiple of the noise estimate are treated as noise and update it
+const NOISE_UPDATE_RATIO: f32 = 3.0;
+const NOISE_ADAPT_RATE: f32 = 0.05;
+// The first frames adapt quickly so the estimate settles within ~half a second
+const NOISE_WARMUP_MS: u32 = 500;
+const NOISE_WARMUP_RATE: f32 = 0.3;
+// How fast the noise estimate may rise when the noise floor goes up (dB per second)
+const NOISE_RISE_DB_PER_SEC: f32 = 5.0;
+
+// Noise gate timing
+const GATE_ATTACK_MS: f32 = 2.0;
+const GATE_RELEASE_MS: f32 = 150.0;
+const GATE_HOLD_MS: f32 = 200.0;
+const GATE_FLOOR_DB: f32 = -40.0;
+
+#[derive(Clone, Copy, Debug)]
+pub struct MicChainSettings {
+    pub highpass_enabled: bool,
+    pub highpass_cutoff_hz: f32,
+    pub suppression_enabled: bool,
+    /// Maximum attenuation of noise-only bins, in dB
+    pub suppression_db: f32,
+    pub gate_enabled: bool,
+    pub gate_threshold_dbfs: f32,
+}
+
+impl MicChainSettings {
+    pub fn any_enabled(&self) -> bool {
+        self.highpass_enabled || self.suppression_enabled || self.gate_enabled
+    }
+}
+
+fn db_to_linear(db: f32) -> f32 {
+    10_f32.powf(db / 20.0)
+}
+
+/// Time constant in samples converted to a one-pole smoothing coefficient
+fn smoothing_coeff(ms: f32, sample_rate: u32) -> f32 {
+    1.0 - (-1000.0 / (ms.max(0.01) * sample_rate as f32)).exp()
+}
+
+/// Second-order Butterworth high-pass (RBJ cookbook)
+pub fn apply_highpass(samples: &[f32], sample_rate: u32, cutoff_hz: f32) -> Vec<f32> {
+    use std::f32::consts::PI;
+
+    let cutoff = cutoff_hz.clamp(10.0, sample_rate as f32 * 0.45);
+    let w0 = 2.0 * PI * cutoff / sample_rate as f32;
+    let alpha = w0.sin() / (2.0 * std::f32::consts::FRAC_1_SQRT_2);
+    let cos_w0 = w0.cos();
+    let a0 = 1.0 + alpha;
+    let b0 = (1.0 + cos_w0) / 2.0 / a0;
+    let b1 = -(1.0 + cos_w0) / a0;
+    let b2 = b0;
+    let a1 = -2.0 * cos_w0 / a0;
+    let a2 = (1.0 - alpha) / a0;
+
+    let (mut x1, mut x2, mut y1, mut y2) = (0.0f32, 0.0f32, 0.0f32, 0.0f32);
+    samples
+        .iter()
+        .map(|&x| {
+            let y = b0 * x + b1 * x1 + b2 * x2 - a1 * y1 - a2 * y2;
+            x2 = x1;
+            x1 = x;
+            y2 = y1;
+            y1 = y;
+            y
+        })
+        .collect()
+}
+
+/// Spectral noise suppression: STFT with 50% overlap, per-bin noise floor tracking
+/// and smoothed spectral subtraction gains.
+pub fn apply_spectral_suppression(samples: &[f32], sample_rate: u32, max_reduction_db: f32) -> Vec<f32> {
+    let fft_size = ((sample_rate / 50) as usize).next_power_of_two().max(256);
+    let hop = fft_size / 2;
+    let bins = fft_size / 2 + 1;
+    let floor = db_to_linear(-max_reduction_db.abs());
+    let rise = db_to_linear(NOISE_RISE_DB_PER_SEC * hop as f32 / sample_rate as f32).powi(2);
+
+    // Periodic Hann window: overlapping frames at 50% sum to exactly 1
+    let window: Vec<f32> = (0..fft_size)
+        .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / fft_size as f32).cos())
+        .collect();
+
+    let mut planner = FftPlanner::<f32>::new();
+    let forward = planner.plan_fft_forward(fft_size);
+    let inverse = planner.plan_fft_inverse(fft_size);
+
+    // Pad so every real sample is covered by two frames
+    let mut padded = vec![0.0f32; hop];
+    padded.extend_from_slice(samples);
+    padded.resize(padded.len() + fft_size, 0.0);
+    let mut output = vec![0.0f32; padded.len()];
+
+    let mut smoothed_power = vec![0.0f32; bins];
+    let mut noise_power = vec![0.0f32; bins];
+    let mut gains = vec![1.0f32; bins];
+    let mut buffer = vec![Complex::new(0.0f32, 0.0); fft_size];
+
+    let warmup_frames = (sample_rate * NOISE_WARMUP_MS / 1000) as usize / hop;
+    let mut frame_index = 0;
+    let mut start = 0;
+    while start + fft_size <= padded.len() {
+        for (i, slot) in buffer.iter_mut().enumerate() {
+            *slot = Complex::new(padded[start + i] * window[i], 0.0);
+        }
+        forward.process(&mut buffer);
+
+        for (smoothed, bin) in smoothed_power.iter_mut().zip(&buffer) {
+            *smoothed = POWER_SMOOTHING * *smoothed + (1.0 - POWER_SMOOTHING) * bin.norm_sqr();
+        }
+
+        for k in 0..bins {
+            let current = smoothed_power[k];
+            noise_power[k] = if frame_index < warmup_frames {
+                noise_power[k] + (current - noise_power[k]) * NOISE_WARMUP_RATE
+            } else if current < NOISE_UPDATE_RATIO * noise_power[k] {
+                noise_power[k] + (current - noise_power[k]) * NOISE_ADAPT_RATE
+            } else {
+                // Probably speech: only let the floor creep up slowly
+                noise_power[k] * rise
+            };
+
+            let target = if current > 0.0 {
+                (1.0 - OVER_SUBTRACTION * noise_power[k] / current).max(floor)
+            } else {
+                floor
+            };
+            gains[k] = GAIN_SMOOTHING * gains[k] + (1.0 - GAIN_SMOOTHING) * target;
+
+            buffer[k] *= gains[k];
+            if k > 0 && k < fft_size - k {
+                buffer[fft_size - k] *= gains[k];
+            }
+        }
+
+        inverse.process(&mut buffer);
+        let scale = 1.0 / fft_size as f32;
+        for (i, value) in buffer.iter().enumerate() {
+            output[start + i] += value.re * scale;
+        }
+
+        start += hop;
+        frame_index += 1;
+    }
+
+    output[hop..hop + samples.len()].to_vec()
+}
+
+/// Noise gate with hold time: attenuates the signal to a fixed floor while the
+/// envelope stays under the threshold.
+pub fn apply_noise_gate(samples: &[f32], sample_rate: u32, threshold_dbfs: f32) -> Vec<f32> {
+    let threshold = db_to_linear(threshold_dbfs);
+    let floor = db_to_linear(GATE_FLOOR_DB);
+    let attack = smoothing_coeff(GATE_ATTACK_MS, sample_rate);
+    let release = smoothing_coeff(GATE_RELEASE_MS, sample_rate);
+    let envelope_release = smoothing_coeff(20.0, sample_rate);
+    let hold_samples = (GATE_HOLD_MS * sample_rate as f32 / 1000.0) as usize;
+
+    let mut envelope = 0.0f32;
+    let mut gain = floor;
+    let mut hold = 0usize;
+
+    samples
+        .iter()
+        .map(|&s| {
+            let level = s.abs();
+            envelope = if level > envelope {
+                level
+            } else {
+                envelope + (level - envelope) * envelope_release
+            };
+
+            if envelope >= threshold {
+                hold = hold_samples;
+            } else {
+                hold = hold.saturating_sub(1);
+            }
+
+            let target = if hold > 0 { 1.0 } else { floor };
+            let coeff = if target > gain { attack } else { release };
+            gain += (target - gain) * coeff;
+            s * gain
+        })
+        .collect()
+}
+
+/// Run the enabled stages on a mono mic track
+pub fn process_mic_track(samples: &[f32], sample_rate: u32, settings: &MicChainSettings) -> Vec<f32> {
+    let mut track = samples.to_vec();
+
+    if settings.highpass_enabled {
+        track = apply_highpass(&track, sample_rate, settings.highpass_cutoff_hz);
+    }
+    if settings.suppression_enabled {
+        track = apply_spectral_suppression(&track, sample_rate, settings.suppression_db);
+    }
+    if settings.gate_enabled {
+        track = apply_noise_gate(&track, sample_rate, settings.gate_threshold_dbfs);
+    }
+
+    track
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 48000;
+
+    fn sine(frequency: f32, amplitude: f32, seconds: f32) -> Vec<f32> {
+        (0..(RATE as f32 * seconds) as usize)
+            .map(|i| amplitude * (2.0 * std::f32::consts::PI * frequency * i as f32 / RATE as f32).sin())
+            .collect()
+    }
+
+    /// RMS in dB of the last half of a signal, once filters have settled
+    fn settled_rms_db(samples: &[f32]) -> f32 {
+        let tail = &samples[samples.len() / 2..];
+        let mean_square = tail.iter().map(|s| s * s).sum::<f32>() / tail.len() as f32;
+        10.0 * mean_square.max(1e-20).log10()
+    }
+
+    #[test]
+    fn highpass_removes_hum_and_keeps_voice() {
+        let hum = sine(30.0, 0.5, 2.0);
+        let voice = sine(1000.0, 0.5, 2.0);
+        let hum_loss = settled_rms_db(&hum) - settled_rms_db(&apply_highpass(&hum, RATE, 100.0));
+        let voice_loss = settled_rms_db(&voice) - settled_rms_db(&apply_highpass(&voice, RATE, 100.0));
+        assert!(hum_loss > 18.0, "30 Hz attenuated by {} dB", hum_loss);
+        assert!(voice_loss.abs() < 0.5, "1 kHz attenuated by {} dB", voice_loss);
+    }
+
+    #[test]
+    fn gate_closes_below_threshold_and_opens_above() {
+        let quiet = sine(440.0, db_to_linear(-60.0), 1.0);
+        let gated = apply_noise_gate(&quiet, RATE, -40.0);
+        let reduction = settled_rms_db(&quiet) - settled_rms_db(&gated);
+        assert!((reduction + GATE_FLOOR_DB).abs() < 1.0, "quiet signal reduced by {} dB", reduction);
+
+        let loud = sine(440.0, db_to_linear(-20.0), 1.0);
+        let passed = settled_rms_db(&loud) - settled_rms_db(&apply_noise_gate(&loud, RATE, -40.0));
+        assert!(passed.abs() < 0.1, "loud signal reduced by {} dB", passed);
+    }
+
+    #[test]
+    fn suppression_keeps_length_and_reduces_steady_noise() {
+        // Deterministic white noise
+        let mut seed = 0x2545_f491_u32;
+        let noise: Vec<f32> = (0..RATE as usize * 2 + 123)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                (seed as f32 / u32::MAX as f32 - 0.5) * 0.02
+            })
+            .collect();
+        for len in [0, 100, 1024, 4801, noise.len()] {
+            assert_eq!(apply_spectral_suppression(&noise[..len], RATE, 12.0).len(), len);
+        }
+        let reduction = settled_rms_db(&noise) - settled_rms_db(&apply_spectral_suppression(&noise, RATE, 12.0));
+        assert!(reduction > 6.0, "noise reduced by {} dB", reduction);
+    }
+}
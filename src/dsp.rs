@@ -0,0 +1,282 @@
+use std::collections::VecDeque;
+
+// Limiter ceiling, slightly below full scale to leave room for the encoder
+pub const LIMITER_CEILING: f32 = 0.97;
+const LIMITER_LOOKAHEAD_MS: f32 = 5.0;
+const LIMITER_RELEASE_MS: f32 = 80.0;
+
+// EBU R128 gating parameters
+const LOUDNESS_BLOCK_MS: u32 = 400;
+const LOUDNESS_HOP_MS: u32 = 100;
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+const RELATIVE_GATE_LU: f64 = -10.0;
+
+// Maximum gain applied by loudness normalization, so near-silent files are not blown up
+const MAX_NORMALIZATION_GAIN_DB: f64 = 30.0;
+
+/// Result of the loudness stage, written into the file metadata
+#[derive(Clone, Copy, Debug)]
+pub struct LoudnessReport {
+    /// Integrated loudness of the mixdown before normalization (None if fully gated)
+    pub measured_lufs: Option<f64>,
+    /// Integrated loudness of the exported signal
+    pub output_lufs: Option<f64>,
+    /// Gain applied by normalization, in dB
+    pub gain_db: f64,
+}
+
+/// Simple direct form I biquad
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 3],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 3]) -> Self {
+        Self { b, a, x: [0.0; 2], y: [0.0; 2] }
+    }
+
+    fn process(&mut self, input: f64) -> f64 {
+        let output = self.b[0] * input + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[1] * self.y[0]
+            - self.a[2] * self.y[1];
+        self.x = [input, self.x[0]];
+        self.y = [output, self.y[0]];
+        output
+    }
+}
+
+/// Build the two-stage K-weighting filter from ITU-R BS.1770 for any sample rate
+fn k_weighting_filters(sample_rate: u32) -> (Biquad, Biquad) {
+    use std::f64::consts::PI;
+    let rate = sample_rate as f64;
+
+    // Stage 1: high shelf modelling the acoustic effect of the head
+    let f0 = 1681.974450955533;
+    let gain_db = 3.999843853973347;
+    let q = 0.7071752369554196;
+    let k = (PI * f0 / rate).tan();
+    let vh = 10_f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad::new(
+        [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    // Stage 2: RLB high-pass
+    let f0 = 38.13547087602444;
+    let q = 0.5003270373238773;
+    let k = (PI * f0 / rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let highpass = Biquad::new(
+        [1.0, -2.0, 1.0],
+        [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    (shelf, highpass)
+}
+
+fn mean_square_to_lufs(mean_square: f64) -> f64 {
+    -0.691 + 10.0 * mean_square.max(1e-20).log10()
+}
+
+/// Measure the integrated loudness (EBU R128 / BS.1770) of a mono signal.
+/// Returns None if the signal is shorter than one block or entirely below the absolute gate.
+pub fn measure_integrated_loudness(samples: &[f32], sample_rate: u32) -> Option<f64> {
+    let block_len = (sample_rate * LOUDNESS_BLOCK_MS / 1000) as usize;
+    let hop_len = (sample_rate * LOUDNESS_HOP_MS / 1000) as usize;
+    if block_len == 0 || hop_len == 0 || samples.len() < block_len {
+        return None;
+    }
+
+    let (mut shelf, mut highpass) = k_weighting_filters(sample_rate);
+    let weighted: Vec<f64> = samples
+        .iter()
+        .map(|&s| highpass.process(shelf.process(s as f64)))
+        .collect();
+
+    // Mean square per 400 ms block with 75% overlap
+    let mut blocks = Vec::new();
+    let mut start = 0;
+    while start + block_len <= weighted.len() {
+        let energy: f64 = weighted[start..start + block_len].iter().map(|s| s * s).sum();
+        blocks.push(energy / block_len as f64);
+        start += hop_len;
+    }
+
+    let above_absolute: Vec<f64> = blocks
+        .into_iter()
+        .filter(|&ms| mean_square_to_lufs(ms) > ABSOLUTE_GATE_LUFS)
+        .collect();
+    if above_absolute.is_empty() {
+        return None;
+    }
+
+    let relative_gate = mean_square_to_lufs(
+        above_absolute.iter().sum::<f64>() / above_absolute.len() as f64,
+    ) + RELATIVE_GATE_LU;
+
+    let gated: Vec<f64> = above_absolute
+        .into_iter()
+        .filter(|&ms| mean_square_to_lufs(ms) > relative_gate)
+        .collect();
+    if gated.is_empty() {
+        return None;
+    }
+
+    Some(mean_square_to_lufs(gated.iter().sum::<f64>() / gated.len() as f64))
+}
+
+/// Look-ahead soft limiter: the gain starts ramping down before a peak arrives,
+/// so the output never exceeds `ceiling` and no hard clipping occurs.
+pub fn apply_soft_limiter(samples: &[f32], sample_rate: u32, ceiling: f32) -> Vec<f32> {
+    let lookahead = ((sample_rate as f32 * LIMITER_LOOKAHEAD_MS / 1000.0) as usize).max(1);
+    let release_coeff = 1.0 - (-1.0 / (sample_rate as f32 * LIMITER_RELEASE_MS / 1000.0)).exp();
+
+    // Gain needed at each sample to stay under the ceiling
+    let required: Vec<f32> = samples
+        .iter()
+        .map(|&s| {
+            let peak = s.abs();
+            if peak > ceiling { ceiling / peak } else { 1.0 }
+        })
+        .collect();
+
+    // Minimum required gain over the look-ahead window (monotonic deque)
+    let len = required.len();
+    let mut window_min = vec![1.0f32; len];
+    let mut deque: VecDeque<usize> = VecDeque::new();
+    for i in (0..len).rev() {
+        while let Some(&back) = deque.back() {
+            if required[back] >= required[i] {
+                deque.pop_back();
+            } else {
+                break;
+            }
+        }
+        deque.push_back(i);
+        while let Some(&front) = deque.front() {
+            if front >= i + lookahead {
+                deque.pop_front();
+            } else {
+                break;
+            }
+        }
+        window_min[i] = required[*deque.front().unwrap()];
+    }
+
+    // Averaging the window minimum over the same span gives a smooth attack ramp
+    // that still reaches the required gain by the time the peak is output
+    let mut output = Vec::with_capacity(len);
+    let mut running_sum = 0.0f32;
+    let mut gain = 1.0f32;
+    for i in 0..len {
+        running_sum += window_min[i];
+        if i >= lookahead {
+            running_sum -= window_min[i - lookahead];
+        }
+        let span = (i + 1).min(lookahead);
+        let padding = lookahead - span;
+        let attack_gain = (running_sum + padding as f32) / lookahead as f32;
+        let attack_gain = attack_gain.min(required[i]);
+
+        gain = if attack_gain < gain {
+            attack_gain
+        } else {
+            gain + (attack_gain - gain) * release_coeff
+        };
+        output.push(samples[i] * gain);
+    }
+
+    output
+}
+
+/// Loudness stage of the export chain: measure, optionally normalize towards
+/// `target_lufs`, then run the soft limiter.
+pub fn process_for_export(
+    samples: &[f32],
+    sample_rate: u32,
+    target_lufs: Option<f32>,
+) -> (Vec<f32>, LoudnessReport) {
+    let measured_lufs = measure_integrated_loudness(samples, sample_rate);
+
+    let gain_db = match (target_lufs, measured_lufs) {
+        (Some(target), Some(measured)) => {
+            (target as f64 - measured).min(MAX_NORMALIZATION_GAIN_DB)
+        }
+        _ => 0.0,
+    };
+
+    let gain = 10_f64.powf(gain_db / 20.0) as f32;
+    let gained: Vec<f32> = samples.iter().map(|&s| s * gain).collect();
+    let limited = apply_soft_limiter(&gained, sample_rate, LIMITER_CEILING);
+    let output_lufs = measure_integrated_loudness(&limited, sample_rate);
+
+    (
+        limited,
+        LoudnessReport {
+            measured_lufs,
+            output_lufs,
+            gain_db,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 48000;
+
+    fn sine(frequency: f32, amplitude: f32, seconds: f32) -> Vec<f32> {
+        (0..(RATE as f32 * seconds) as usize)
+            .map(|i| amplitude * (2.0 * std::f32::consts::PI * frequency * i as f32 / RATE as f32).sin())
+            .collect()
+    }
+
+    #[test]
+    fn sine_at_minus_23_lufs_measures_minus_23() {
+        // The -0.691 offset cancels the K-weighting gain at 1 kHz, so the loudness is
+        // the mean square A²/2 in dB (a full-scale sine reads -3.01 LUFS)
+        let amplitude = (2.0 * 10f64.powf(-23.0 / 10.0)).sqrt() as f32;
+        let measured = measure_integrated_loudness(&sine(1000.0, amplitude, 10.0), RATE).unwrap();
+        assert!((measured + 23.0).abs() <= 0.5, "measured {} LUFS", measured);
+    }
+
+    #[test]
+    fn silence_and_short_signals_have_no_loudness() {
+        assert_eq!(measure_integrated_loudness(&vec![0.0; RATE as usize * 2], RATE), None);
+        assert_eq!(measure_integrated_loudness(&sine(1000.0, 0.5, 0.2), RATE), None);
+    }
+
+    #[test]
+    fn limiter_output_never_exceeds_ceiling() {
+        // Quiet tone with loud bursts and isolated full-scale spikes
+        let mut samples = sine(440.0, 0.3, 2.0);
+        for (i, sample) in samples.iter_mut().enumerate() {
+            if (i / 4800) % 3 == 1 {
+                *sample *= 6.0;
+            }
+            if i % 7919 == 0 {
+                *sample = if i % 2 == 0 { 1.8 } else { -1.8 };
+            }
+        }
+        let limited = apply_soft_limiter(&samples, RATE, LIMITER_CEILING);
+        assert_eq!(limited.len(), samples.len());
+        let peak = limited.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+        assert!(peak <= LIMITER_CEILING, "peak {}", peak);
+    }
+
+    #[test]
+    fn limiter_leaves_signals_under_the_ceiling_alone() {
+        let samples = sine(440.0, 0.5, 1.0);
+        assert_eq!(apply_soft_limiter(&samples, RATE, LIMITER_CEILING), samples);
+    }
+}
@@ -0,0 +1,237 @@
+// Client of the freedesktop Secret Service (GNOME Keyring, KWallet, KeePassXC...) on the
+// session bus, through GDBus. Secrets are exchanged in a "plain" session: the session
+// bus only connects processes of the user. Locked items and collections are unlocked
+// first, which may show the keyring's password prompt.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::Duration;
+
+use gtk4::gio;
+use gtk4::glib::{self, variant::ObjectPath, prelude::*};
+
+const SERVICE: &str = "org.freedesktop.secrets";
+const SERVICE_PATH: &str = "/org/freedesktop/secrets";
+const SERVICE_INTERFACE: &str = "org.freedesktop.Secret.Service";
+const COLLECTION_INTERFACE: &str = "org.freedesktop.Secret.Collection";
+const ITEM_INTERFACE: &str = "org.freedesktop.Secret.Item";
+const PROMPT_INTERFACE: &str = "org.freedesktop.Secret.Prompt";
+// "/" stands for no object (no prompt needed, no such alias)
+const NO_OBJECT: &str = "/";
+const CALL_TIMEOUT_MS: i32 = 10_000;
+// Time left to the user to answer an unlock prompt
+const PROMPT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Secret stored with exactly these attributes, if any
+pub fn lookup(attributes: &[(&str, &str)]) -> Result<Option<String>, String> {
+    let service = Service::connect()?;
+    let Some(item) = service.search(attributes)?.into_iter().next() else {
+        return Ok(None);
+    };
+    let reply = service.call(
+        SERVICE_PATH,
+        SERVICE_INTERFACE,
+        "GetSecrets",
+        (vec![object_path(&item)?], object_path(&service.session)?).to_variant(),
+    )?;
+    // a{o(oayays)}: the secret value is the third member of the struct
+    let secrets = reply.child_value(0);
+    if secrets.n_children() == 0 {
+        return Ok(None);
+    }
+    let value = secrets.child_value(0).child_value(1).child_value(2);
+    let bytes = value.fixed_array::<u8>().map_err(|e| e.to_string())?;
+    String::from_utf8(bytes.to_vec())
+        .map(Some)
+        .map_err(|_| "the stored secret is not UTF-8".to_string())
+}
+
+/// Store a secret in the default collection, replacing the one with the same attributes
+pub fn store(label: &str, attributes: &[(&str, &str)], secret: &str) -> Result<(), String> {
+    let service = Service::connect()?;
+    let collection = service.default_collection()?;
+    let properties: HashMap<String, glib::Variant> = HashMap::from([
+        (format!("{}.Label", ITEM_INTERFACE), label.to_variant()),
+        (format!("{}.Attributes", ITEM_INTERFACE), attribute_map(attributes).to_variant()),
+    ]);
+    let secret = (
+        object_path(&service.session)?,
+        Vec::<u8>::new(),
+        secret.as_bytes().to_vec(),
+        "text/plain",
+    );
+    let reply = service.call(
+        &collection,
+        COLLECTION_INTERFACE,
+        "CreateItem",
+        (properties, secret, true).to_variant(),
+    )?;
+    service.prompt(&string_of(&reply.child_value(1)))?;
+    Ok(())
+}
+
+/// Delete the secrets stored with these attributes
+pub fn clear(attributes: &[(&str, &str)]) -> Result<(), String> {
+    let service = Service::connect()?;
+    for item in service.search(attributes)? {
+        let reply = service.call(&item, ITEM_INTERFACE, "Delete", ().to_variant())?;
+        service.prompt(&string_of(&reply.child_value(0)))?;
+    }
+    Ok(())
+}
+
+/// Whether a Secret Service answers on the session bus, with the reason when not
+pub fn check_available() -> Result<(), String> {
+    Service::connect().map(|_| ())
+}
+
+struct Service {
+    connection: gio::DBusConnection,
+    session: String,
+}
+
+impl Service {
+    fn connect() -> Result<Self, String> {
+        let connection = gio::bus_get_sync(gio::BusType::Session, gio::Cancellable::NONE)
+            .map_err(|e| format!("no session bus: {}", e))?;
+        let mut service = Service {
+            connection,
+            session: NO_OBJECT.to_string(),
+        };
+        let reply = service
+            .call(SERVICE_PATH, SERVICE_INTERFACE, "OpenSession", ("plain", "".to_variant()).to_variant())
+            .map_err(|e| format!("no Secret Service (GNOME Keyring, KWallet...) on the session bus: {}", e))?;
+        service.session = string_of(&reply.child_value(1));
+        Ok(service)
+    }
+
+    fn call(&self, path: &str, interface: &str, method: &str, parameters: glib::Variant) -> Result<glib::Variant, String> {
+        self.connection
+            .call_sync(
+                Some(SERVICE),
+                path,
+                interface,
+                method,
+                Some(&parameters),
+                None,
+                gio::DBusCallFlags::NONE,
+                CALL_TIMEOUT_MS,
+                gio::Cancellable::NONE,
+            )
+            .map_err(|e| format!("{} failed: {}", method, e.message()))
+    }
+
+    /// Unlocked items matching the attributes; locked ones are unlocked first
+    fn search(&self, attributes: &[(&str, &str)]) -> Result<Vec<String>, String> {
+        let reply = self.call(SERVICE_PATH, SERVICE_INTERFACE, "SearchItems", (attribute_map(attributes),).to_variant())?;
+        let mut items = strings_of(&reply.child_value(0));
+        let locked = strings_of(&reply.child_value(1));
+        if !locked.is_empty() {
+            items.extend(self.unlock(&locked)?);
+        }
+        Ok(items)
+    }
+
+    /// Path of the default collection, unlocked
+    fn default_collection(&self) -> Result<String, String> {
+        let reply = self.call(SERVICE_PATH, SERVICE_INTERFACE, "ReadAlias", ("default",).to_variant())?;
+        let collection = string_of(&reply.child_value(0));
+        if collection == NO_OBJECT {
+            return Err("the keyring has no default collection".to_string());
+        }
+        self.unlock(std::slice::from_ref(&collection))?;
+        Ok(collection)
+    }
+
+    fn unlock(&self, objects: &[String]) -> Result<Vec<String>, String> {
+        let paths = objects.iter().map(|path| object_path(path)).collect::<Result<Vec<_>, _>>()?;
+        let reply = self.call(SERVICE_PATH, SERVICE_INTERFACE, "Unlock", (paths,).to_variant())?;
+        let mut unlocked = strings_of(&reply.child_value(0));
+        // The objects unlocked through the prompt are its result
+        if let Some(result) = self.prompt(&string_of(&reply.child_value(1)))? {
+            unlocked.extend(strings_of(&result));
+        }
+        if unlocked.is_empty() {
+            return Err("the keyring is locked".to_string());
+        }
+        Ok(unlocked)
+    }
+
+    /// Show a prompt of the service and wait for its answer. Returns its result, or
+    /// None when no prompt was needed.
+    fn prompt(&self, prompt: &str) -> Result<Option<glib::Variant>, String> {
+        if prompt == NO_OBJECT {
+            return Ok(None);
+        }
+        // The Completed signal is dispatched in the thread-default context at
+        // subscription: a private one, iterated here until it arrives
+        let context = glib::MainContext::new();
+        context
+            .with_thread_default(|| {
+                let completed: Rc<RefCell<Option<(bool, glib::Variant)>>> = Rc::new(RefCell::new(None));
+                let completed_clone = Rc::clone(&completed);
+                let subscription = self.connection.signal_subscribe(
+                    None,
+                    Some(PROMPT_INTERFACE),
+                    Some("Completed"),
+                    Some(prompt),
+                    None,
+                    gio::DBusSignalFlags::NONE,
+                    move |_, _, _, _, _, parameters| {
+                        let dismissed = parameters.child_value(0).get::<bool>().unwrap_or(true);
+                        let result = parameters.child_value(1).as_variant().unwrap_or_else(|| "".to_variant());
+                        *completed_clone.borrow_mut() = Some((dismissed, result));
+                    },
+                );
+                let timeout = glib::timeout_source_new(PROMPT_TIMEOUT, None, glib::Priority::DEFAULT, || {
+                    glib::ControlFlow::Break
+                });
+                timeout.attach(Some(&context));
+
+                let result = self.call(prompt, PROMPT_INTERFACE, "Prompt", ("",).to_variant()).and_then(|_| {
+                    while completed.borrow().is_none() && !timeout.is_destroyed() {
+                        context.iteration(true);
+                    }
+                    match completed.take() {
+                        Some((false, result)) => Ok(Some(result)),
+                        Some((true, _)) => Err("the keyring prompt was dismissed".to_string()),
+                        None => Err("no answer to the keyring prompt".to_string()),
+                    }
+                });
+                timeout.destroy();
+                self.connection.signal_unsubscribe(subscription);
+                result
+            })
+            .map_err(|e| e.to_string())?
+    }
+}
+
+impl Drop for Service {
+    fn drop(&mut self) {
+        if self.session != NO_OBJECT {
+            let _ = self.call(&self.session, "org.freedesktop.Secret.Session", "Close", ().to_variant());
+        }
+    }
+}
+
+fn attribute_map(attributes: &[(&str, &str)]) -> HashMap<String, String> {
+    attributes
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+fn object_path(path: &str) -> Result<ObjectPath, String> {
+    ObjectPath::try_from(path.to_string()).map_err(|_| format!("invalid object path {}", path))
+}
+
+/// Value of a string or object path variant
+fn string_of(variant: &glib::Variant) -> String {
+    variant.str().unwrap_or(NO_OBJECT).to_string()
+}
+
+/// Values of an array of object paths
+fn strings_of(variant: &glib::Variant) -> Vec<String> {
+    variant.iter().map(|child| string_of(&child)).collect()
+}
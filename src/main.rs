@@ -1,8 +1,28 @@
+mod aec;
+mod agc;
+mod cli;
+mod control;
+mod denoise;
+mod dsp;
+mod keyring;
+mod markers;
+mod meter;
 mod notifier;
+mod shortcuts;
+mod speakers;
+mod spectrum;
+mod upload_auth;
+mod upload_history;
+mod upload_queue;
+mod upload_request;
+mod upload_progress;
+mod upload_s3;
+mod upload_webdav;
 
 use gtk4::prelude::*;
-use gtk4::{glib, Application, ApplicationWindow, Button, Orientation, DrawingArea};
+use gtk4::{gio, glib, Application, ApplicationWindow, Button, Orientation, DrawingArea};
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::time::{Duration, Instant};
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use chrono::Local;
@@ -11,56 +31,374 @@ use std::fs::File;
 use std::rc::Rc;
 use std::cell::RefCell;
 use serde::{Deserialize, Serialize};
-use std::io::{Read, Write};
+use std::io::Read;
 
 const APP_ID: &str = "com.audio.recorder";
 
-// Global window reference for tray icon to toggle
-static WINDOW_VISIBLE: Mutex<Option<Arc<Mutex<bool>>>> = Mutex::new(None);
-
 // Global notifier instance
 static NOTIFIER: Mutex<Option<notifier::Notifier>> = Mutex::new(None);
 
-// Global recording state for tray icon control
+// Snapshot of the recording state, readable from the tray, D-Bus and command line
 static RECORDING_STATE: Mutex<Option<Arc<Mutex<TrayRecordingState>>>> = Mutex::new(None);
 
-// Channel for sending upload dialogs to the main thread
-static UPLOAD_DIALOG_SENDER: Mutex<Option<std::sync::mpsc::Sender<UploadDialogRequest>>> = Mutex::new(None);
+// Tray icon handle, updated as soon as the recording state changes
+static TRAY_HANDLE: Mutex<Option<ksni::Handle<RecorderTray>>> = Mutex::new(None);
+
+// Encode and upload threads still running; quitting waits for them
+static BACKGROUND_TASKS: AtomicUsize = AtomicUsize::new(0);
+// Set once the application is shutting down
+static QUITTING: AtomicBool = AtomicBool::new(false);
+
+type UiHandler = Box<dyn Fn(UiMessage)>;
+
+thread_local! {
+    // Handler for UiMessages, installed by build_ui on the main thread
+    static UI_HANDLER: RefCell<Option<UiHandler>> = const { RefCell::new(None) };
+}
 
 #[derive(Clone, Default)]
 struct TrayRecordingState {
     recording: bool,
     paused: bool,
-    // Signals to trigger actions from tray
-    start_requested: bool,
-    stop_requested: bool,
-    pause_requested: bool,
+    elapsed_secs: u64,
+}
+
+/// Requests from the tray, command line, D-Bus, shortcuts and worker threads,
+/// handled on the main loop
+enum UiMessage {
+    StartRecording,
+    StopRecording,
+    TogglePause,
+    AddMarker,
+    ToggleWindow,
+    ShowWindow,
+    SelectMic(usize),
+    SelectLoopback(Option<usize>),
+    ToggleSystemAudio,
+    UploadDialog(UploadDialogRequest),
+    /// Automatic upload, sent after a countdown unless changed or cancelled
+    UploadCountdown(UploadDialogRequest, u32),
+    /// Speaker counts just sent to a destination, prefilled next time
+    RememberSpeakers(String, SpeakerCounts),
+    UploadQueueChanged,
+    /// The upload in flight started, progressed or ended
+    UploadProgressChanged,
+    ShowUploadQueue,
+    /// The upload secrets of older versions have been moved to their destinations
+    UrlSecretsMigrated,
+    /// `interactive` is false for SIGTERM/SIGINT, where nobody may be there to answer a dialog
+    Quit { interactive: bool },
+}
+
+/// Queue a message for the main loop (callable from any thread)
+fn send_ui_message(message: UiMessage) {
+    glib::idle_add_once(move || {
+        UI_HANDLER.with(|handler| match handler.borrow().as_ref() {
+            Some(handler) => handler(message),
+            None => eprintln!("UI not ready, message dropped"),
+        });
+    });
+}
+
+/// Move the keyring secrets of older versions from their URL to the destinations, so
+/// destinations sharing a URL stop sharing a secret. Blocks while the keyring prompts.
+fn migrate_url_secrets(destinations: &[UploadDestination]) -> Result<(), String> {
+    let destinations: Vec<(&str, &str)> = destinations
+        .iter()
+        .filter(|d| d.needs_secret())
+        .map(|d| (d.name.as_str(), d.url.as_str()))
+        .collect();
+    upload_auth::migrate_url_secrets(&destinations)
+}
+
+/// Counts a background thread for as long as it is alive (create it before spawning)
+struct BackgroundTask;
+
+impl BackgroundTask {
+    fn start() -> Self {
+        BACKGROUND_TASKS.fetch_add(1, Ordering::SeqCst);
+        BackgroundTask
+    }
+}
+
+impl Drop for BackgroundTask {
+    fn drop(&mut self) {
+        BACKGROUND_TASKS.fetch_sub(1, Ordering::SeqCst);
+    }
 }
 
 struct UploadDialogRequest {
     file_path: String,
-    endpoint: String,
+    destinations: Vec<UploadDestination>,
+    default_destination: Option<String>,
     save_locally: bool,
+    /// Speaker counts last sent to each destination
+    last_speakers: std::collections::BTreeMap<String, SpeakerCounts>,
+    /// Estimated from the recording, when long enough
+    estimated_speakers: Option<u32>,
+}
+
+impl UploadDialogRequest {
+    /// Queue the upload to the default destination, with its default speaker counts
+    fn enqueue_with_defaults(&self) {
+        let Some(destination) = find_destination(&self.destinations, self.default_destination.as_deref(), None) else {
+            return;
+        };
+        let speakers = destination.default_speakers;
+        println!("Automatic upload of {} to {}", self.file_path, destination.name);
+        upload_queue::enqueue(&self.file_path, destination, speakers.min, speakers.max, !self.save_locally);
+    }
+}
+
+/// Upload each recording to the default destination without the upload dialog,
+/// optionally after a countdown that lets the user change or cancel it
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct AutoUpload {
+    enabled: bool,
+    countdown_enabled: bool,
+    countdown_secs: u32,
+}
+
+/// Speaker count hints sent with an upload
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+struct SpeakerCounts {
+    min: u32,
+    max: u32,
+}
+
+impl Default for SpeakerCounts {
+    fn default() -> Self {
+        Self { min: 1, max: 2 }
+    }
+}
+
+/// What to do with a running recording when the application quits
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum QuitAction {
+    #[default]
+    Ask,
+    Save,
+    Discard,
+}
+
+/// Where a destination sends recordings
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum UploadTarget {
+    /// HTTP request shaped by the destination's `request` (N8N and other webhooks)
+    #[default]
+    Webhook,
+    /// Object in an S3-compatible bucket; the URL is the service endpoint
+    S3(upload_s3::S3Target),
+    /// File in a WebDAV folder (Nextcloud...); the URL is the base collection
+    Webdav(upload_webdav::WebdavTarget),
+}
+
+impl UploadTarget {
+    fn id(&self) -> &'static str {
+        match self {
+            UploadTarget::Webhook => "webhook",
+            UploadTarget::S3(_) => "s3",
+            UploadTarget::Webdav(_) => "webdav",
+        }
+    }
+}
+
+/// Named upload target, picked in the upload dialog
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+struct UploadDestination {
+    name: String,
+    url: String,
+    #[serde(default)]
+    target: UploadTarget,
+    #[serde(default)]
+    auth: upload_auth::AuthMethod,
+    /// Extra fields sent with every upload to this destination (values are templates)
+    #[serde(default)]
+    fields: std::collections::BTreeMap<String, String>,
+    #[serde(default)]
+    request: upload_request::RequestShape,
+    /// Used by automatic uploads and preselected in the upload dialog
+    #[serde(default)]
+    default_speakers: SpeakerCounts,
+}
+
+impl UploadDestination {
+    /// Whether a secret is kept in the keyring for the destination
+    fn needs_secret(&self) -> bool {
+        match self.target {
+            UploadTarget::S3(_) => true,
+            UploadTarget::Webhook | UploadTarget::Webdav(_) => self.auth.needs_secret(),
+        }
+    }
+    
+    /// Where recordings end up, as shown in the upload dialog
+    fn location(&self) -> String {
+        match &self.target {
+            UploadTarget::Webhook => self.url.clone(),
+            UploadTarget::S3(s3) => format!("s3://{}/{} ({})", s3.bucket, s3.key_template, self.url),
+            UploadTarget::Webdav(webdav) => format!("{}/{}", self.url.trim_end_matches('/'), webdav.folder_template),
+        }
+    }
+}
+
+/// Destination with the given name, or the default one (or the first) without a name
+fn find_destination<'a>(
+    destinations: &'a [UploadDestination],
+    default_destination: Option<&str>,
+    name: Option<&str>,
+) -> Option<&'a UploadDestination> {
+    match name {
+        Some(name) => destinations.iter().find(|d| d.name == name),
+        None => default_destination
+            .and_then(|default| destinations.iter().find(|d| d.name == default))
+            .or_else(|| destinations.first()),
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 struct Config {
-    selected_mic_index: usize,
+    selected_mic_index: Option<usize>,
     selected_loopback_index: Option<usize>,
     mic_gain: f32,
     save_directory: Option<String>,
+    // Single endpoint of older versions, moved to upload_destinations on load
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     n8n_endpoint: Option<String>,
     n8n_enabled: bool,
     save_locally: bool,
+    #[serde(default)]
+    loudness_normalization: bool,
+    #[serde(default = "default_loudness_target")]
+    loudness_target_lufs: f32,
+    #[serde(default)]
+    agc_enabled: bool,
+    #[serde(default = "default_agc_target")]
+    agc_target_dbfs: f32,
+    #[serde(default = "default_agc_attack")]
+    agc_attack_ms: f32,
+    #[serde(default = "default_agc_release")]
+    agc_release_ms: f32,
+    #[serde(default)]
+    highpass_enabled: bool,
+    #[serde(default = "default_highpass_cutoff")]
+    highpass_cutoff_hz: f32,
+    #[serde(default)]
+    noise_suppression_enabled: bool,
+    #[serde(default = "default_noise_suppression")]
+    noise_suppression_db: f32,
+    #[serde(default)]
+    noise_gate_enabled: bool,
+    #[serde(default = "default_noise_gate_threshold")]
+    noise_gate_threshold_dbfs: f32,
+    #[serde(default)]
+    echo_cancellation: bool,
+    #[serde(default = "default_shortcut_toggle_recording")]
+    shortcut_toggle_recording: String,
+    #[serde(default = "default_shortcut_pause")]
+    shortcut_pause: String,
+    #[serde(default = "default_shortcut_marker")]
+    shortcut_marker: String,
+    #[serde(default)]
+    quit_action: QuitAction,
+    // Per-URL authentication of older versions, moved to upload_destinations on load
+    #[serde(default, skip_serializing_if = "std::collections::HashMap::is_empty")]
+    upload_auth: std::collections::HashMap<String, upload_auth::AuthMethod>,
+    #[serde(default)]
+    upload_destinations: Vec<UploadDestination>,
+    /// Name of the destination preselected in the upload dialog
+    #[serde(default)]
+    default_destination: Option<String>,
+    #[serde(default)]
+    auto_upload: bool,
+    #[serde(default)]
+    auto_upload_countdown: bool,
+    #[serde(default = "default_auto_upload_countdown")]
+    auto_upload_countdown_secs: u32,
+    /// Speaker counts last sent to each destination from the upload dialog
+    #[serde(default)]
+    last_speakers: std::collections::BTreeMap<String, SpeakerCounts>,
+    // Older versions keyed the upload secrets by URL; false until they are moved
+    #[serde(default)]
+    secrets_by_destination: bool,
+}
+
+fn default_loudness_target() -> f32 {
+    -16.0
+}
+
+fn default_agc_target() -> f32 {
+    -20.0
+}
+
+fn default_agc_attack() -> f32 {
+    50.0
+}
+
+fn default_agc_release() -> f32 {
+    1500.0
+}
+
+fn default_highpass_cutoff() -> f32 {
+    80.0
 }
 
+fn default_noise_suppression() -> f32 {
+    12.0
+}
+
+fn default_noise_gate_threshold() -> f32 {
+    -50.0
+}
+
+fn default_shortcut_toggle_recording() -> String {
+    "CTRL+ALT+R".to_string()
+}
+
+fn default_shortcut_pause() -> String {
+    "CTRL+ALT+P".to_string()
+}
+
+fn default_shortcut_marker() -> String {
+    "CTRL+ALT+M".to_string()
+}
+
+fn default_auto_upload_countdown() -> u32 {
+    10
+}
+
+// Maximum boost the AGC may apply, matching the range of the manual gain slider
+const AGC_MAX_GAIN_DB: f32 = 20.0;
+
 impl Config {
     fn load() -> Option<Self> {
         let config_path = dirs::config_dir()?.join("audio-recorder").join("config.json");
         let mut file = File::open(config_path).ok()?;
         let mut contents = String::new();
         file.read_to_string(&mut contents).ok()?;
-        serde_json::from_str(&contents).ok()
+        let mut config: Self = serde_json::from_str(&contents).ok()?;
+        config.migrate_upload_endpoint();
+        Some(config)
+    }
+    
+    /// Turn the single N8N endpoint of older configs into a destination
+    fn migrate_upload_endpoint(&mut self) {
+        let Some(url) = self.n8n_endpoint.take() else {
+            return;
+        };
+        if self.upload_destinations.is_empty() && !url.is_empty() {
+            let auth = self.upload_auth.remove(&url).unwrap_or_default();
+            self.upload_destinations.push(UploadDestination {
+                name: "N8N".to_string(),
+                url,
+                auth,
+                ..Default::default()
+            });
+            self.default_destination = Some("N8N".to_string());
+        }
+        self.upload_auth.clear();
     }
     
     fn save(&self) -> std::io::Result<()> {
@@ -71,13 +409,17 @@ impl Config {
         std::fs::create_dir_all(&config_dir)?;
         let config_path = config_dir.join("config.json");
         let json = serde_json::to_string_pretty(self)?;
-        let mut file = File::create(config_path)?;
-        file.write_all(json.as_bytes())?;
-        Ok(())
+        write_file_atomically(&config_path, json.as_bytes())
     }
 }
 
 fn main() -> glib::ExitCode {
+    // Headless subcommands (record, list-sources, upload) run without GTK
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(exit_code) = cli::run(&args) {
+        return exit_code;
+    }
+    
     // Initialize the notifier thread
     *NOTIFIER.lock().unwrap() = Some(notifier::Notifier::new());
     
@@ -96,247 +438,580 @@ fn main() -> glib::ExitCode {
 
     let app = Application::builder()
         .application_id(APP_ID)
+        .flags(gio::ApplicationFlags::HANDLES_COMMAND_LINE)
         .build();
 
-    app.connect_activate(build_ui);
-    
-    // Start tray icon in background
-    std::thread::spawn(|| {
+    // Options a second launch forwards to the running instance
+    for (name, description) in [
+        ("toggle", "Start recording, or stop the current recording"),
+        ("start", "Start recording"),
+        ("stop", "Stop recording"),
+        ("pause", "Pause or resume recording"),
+        ("marker", "Add a marker to the current recording"),
+        ("show", "Show the recorder window"),
+    ] {
+        app.add_main_option(name, glib::Char::from(0u8), glib::OptionFlags::NONE, glib::OptionArg::None, description, None);
+    }
+
+    // Startup only runs in the primary instance: later launches get no UI or tray icon
+    // of their own, their command line is handled here and the process exits
+    app.connect_startup(|app| {
+        // The tray service runs on its own thread
         start_tray_icon();
+        
+        build_ui(app);
+        
+        // SIGINT/SIGTERM go through the same quit path as the tray menu
+        for signum in [libc::SIGINT, libc::SIGTERM] {
+            glib::unix_signal_add_local(signum, move || {
+                request_quit(&format!("signal {}", signum), false);
+                glib::ControlFlow::Continue
+            });
+        }
     });
+    app.connect_command_line(handle_command_line);
+    app.connect_activate(|_| show_window());
 
     app.run()
 }
 
-fn start_tray_icon() {
-    use ksni::TrayService;
+/// Handle the options of this launch, or of a later one forwarded by GApplication
+fn handle_command_line(_app: &Application, command_line: &gio::ApplicationCommandLine) -> i32 {
+    let options = command_line.options_dict();
+    let mut handled = false;
     
-    struct RecorderTray {
-        recording: bool,
-        paused: bool,
+    if options.contains("toggle") {
+        request_toggle_recording("command line");
+        handled = true;
+    }
+    if options.contains("start") {
+        request_start_recording("command line");
+        handled = true;
+    }
+    if options.contains("stop") {
+        request_stop_recording("command line");
+        handled = true;
+    }
+    if options.contains("pause") {
+        request_pause_recording("command line");
+        handled = true;
+    }
+    if options.contains("marker") {
+        request_marker("command line");
+        handled = true;
+    }
+    
+    // Launching again without options (e.g. from the app menu) brings the window up
+    if options.contains("show") || (!handled && command_line.is_remote()) {
+        show_window();
+    }
+    
+    0
+}
+
+/// Dispatch a global shortcut from the desktop portal
+fn handle_shortcut(action: &str) {
+    match action {
+        shortcuts::ACTION_TOGGLE_RECORDING => request_toggle_recording("shortcut"),
+        shortcuts::ACTION_PAUSE => request_pause_recording("shortcut"),
+        shortcuts::ACTION_MARKER => request_marker("shortcut"),
+        _ => {}
+    }
+}
+
+// Number of recordings listed in the tray menu
+const RECENT_RECORDINGS_COUNT: usize = 5;
+// How often the elapsed time and levels shown by the tray are refreshed while recording
+const TRAY_REFRESH: Duration = Duration::from_secs(1);
+
+#[derive(Default)]
+struct RecorderTray {
+    recording: bool,
+    paused: bool,
+    elapsed_secs: u64,
+    // RMS levels in dBFS, shown while recording
+    mic_level_db: f32,
+    system_level_db: f32,
+    sources: Vec<AudioSource>,
+    selected_mic_index: Option<usize>,
+    selected_loopback_index: Option<usize>,
+    save_directory: Option<String>,
+    recent_recordings: Vec<String>,
+    pending_uploads: usize,
+    upload: Option<upload_progress::Transfer>,
+    recent_uploads: Vec<upload_history::UploadRecord>,
+}
+
+impl RecorderTray {
+    fn status_text(&self) -> String {
+        let state = if self.paused { "⏸" } else { "⏺" };
+        format!(
+            "{} {} · 🎤 {:.0} dB · 🔊 {:.0} dB",
+            state,
+            format_duration(self.elapsed_secs),
+            self.mic_level_db,
+            self.system_level_db
+        )
     }
     
-    impl ksni::Tray for RecorderTray {
-        fn id(&self) -> String {
-            "audio-recorder".to_string()
+    /// Radio group over the sources of one kind; `none_label` adds a first "none" entry
+    fn source_radio_group(&self, monitor: bool, none_label: Option<&str>) -> ksni::MenuItem<Self> {
+        use ksni::menu::*;
+        
+        let mut indices: Vec<Option<usize>> = Vec::new();
+        let mut options = Vec::new();
+        if let Some(label) = none_label {
+            indices.push(None);
+            options.push(RadioItem { label: label.to_string(), ..Default::default() });
+        }
+        for (idx, source) in self.sources.iter().enumerate().filter(|(_, s)| s.is_monitor == monitor) {
+            indices.push(Some(idx));
+            options.push(RadioItem {
+                label: source.display_name.clone(),
+                enabled: !self.recording,
+                ..Default::default()
+            });
         }
         
-        fn title(&self) -> String {
-            if self.recording {
-                if self.paused {
-                    "Audio Recorder (Paused)".to_string()
-                } else {
-                    "Audio Recorder (Recording...)".to_string()
+        let current = if monitor { self.selected_loopback_index } else { self.selected_mic_index };
+        RadioGroup {
+            // Out of range when the selection has no entry (no mic): nothing is checked
+            selected: indices.iter().position(|&idx| idx == current).unwrap_or(indices.len()),
+            select: Box::new(move |_, choice| {
+                let Some(&idx) = indices.get(choice) else {
+                    return;
+                };
+                match (monitor, idx) {
+                    (true, idx) => send_ui_message(UiMessage::SelectLoopback(idx)),
+                    (false, Some(idx)) => send_ui_message(UiMessage::SelectMic(idx)),
+                    (false, None) => {}
                 }
-            } else {
-                "Audio Recorder".to_string()
-            }
+            }),
+            options,
         }
-        
-        fn icon_name(&self) -> String {
-            if self.recording && !self.paused {
-                "media-record".to_string()
+        .into()
+    }
+}
+
+impl ksni::Tray for RecorderTray {
+    fn id(&self) -> String {
+        "audio-recorder".to_string()
+    }
+
+    fn title(&self) -> String {
+        if self.recording {
+            if self.paused {
+                "Audio Recorder (Paused)".to_string()
             } else {
-                "audio-input-microphone".to_string()
+                "Audio Recorder (Recording...)".to_string()
             }
+        } else {
+            "Audio Recorder".to_string()
         }
-        
-        fn activate(&mut self, _x: i32, _y: i32) {
-            // Called on activation (click behavior varies by DE)
-            toggle_window_visibility();
+    }
+
+    fn icon_name(&self) -> String {
+        if self.recording && !self.paused {
+            "media-record".to_string()
+        } else {
+            "audio-input-microphone".to_string()
+        }
+    }
+    
+    fn tool_tip(&self) -> ksni::ToolTip {
+        let mut lines = Vec::new();
+        if self.recording {
+            lines.push(self.status_text());
+        }
+        if let Some(upload) = &self.upload {
+            lines.push(format!("⬆ {} · {} %", upload.label, upload.percent()));
         }
+        ksni::ToolTip {
+            title: self.title(),
+            description: lines.join("\n"),
+            ..Default::default()
+        }
+    }
+
+    fn activate(&mut self, _x: i32, _y: i32) {
+        // Called on activation (click behavior varies by DE)
+        toggle_window_visibility();
+    }
+
+    fn secondary_activate(&mut self, _x: i32, _y: i32) {
+        // Called on middle click
+        toggle_window_visibility();
+    }
+
+    fn menu(&self) -> Vec<ksni::MenuItem<Self>> {
+        use ksni::menu::*;
+        
+        let mut items = Vec::new();
         
-        fn secondary_activate(&mut self, _x: i32, _y: i32) {
-            // Called on middle click
-            toggle_window_visibility();
+        // Elapsed time and levels while recording
+        if self.recording {
+            items.push(StandardItem {
+                label: self.status_text(),
+                enabled: false,
+                ..Default::default()
+            }.into());
         }
         
-        fn menu(&self) -> Vec<ksni::MenuItem<Self>> {
-            use ksni::menu::*;
-            
-            let mut items = vec![
-                StandardItem {
-                    label: "Show/Hide".to_string(),
-                    activate: Box::new(|_| {
-                        toggle_window_visibility();
-                    }),
-                    ..Default::default()
-                }.into(),
-            ];
-            
-            // Add separator
-            items.push(ksni::MenuItem::Separator);
-            
-            // Recording controls based on state
-            if !self.recording {
+        items.push(StandardItem {
+            label: "Show/Hide".to_string(),
+            activate: Box::new(|_| {
+                toggle_window_visibility();
+            }),
+            ..Default::default()
+        }.into());
+        
+        // Add separator
+        items.push(ksni::MenuItem::Separator);
+        
+        // Recording controls based on state
+        if !self.recording {
+            items.push(StandardItem {
+                label: "▶ Start Recording".to_string(),
+                activate: Box::new(|_| {
+                    request_start_recording("tray");
+                }),
+                ..Default::default()
+            }.into());
+        } else {
+            // Recording in progress
+            if self.paused {
                 items.push(StandardItem {
-                    label: "▶ Start Recording".to_string(),
+                    label: "▶ Resume".to_string(),
                     activate: Box::new(|_| {
-                        request_start_recording();
+                        request_pause_recording("tray");
                     }),
                     ..Default::default()
                 }.into());
             } else {
-                // Recording in progress
-                if self.paused {
-                    items.push(StandardItem {
-                        label: "▶ Resume".to_string(),
-                        activate: Box::new(|_| {
-                            request_pause_recording();
-                        }),
-                        ..Default::default()
-                    }.into());
-                } else {
-                    items.push(StandardItem {
-                        label: "⏸ Pause".to_string(),
-                        activate: Box::new(|_| {
-                            request_pause_recording();
-                        }),
-                        ..Default::default()
-                    }.into());
-                }
-                
                 items.push(StandardItem {
-                    label: "⏹ Stop Recording".to_string(),
+                    label: "⏸ Pause".to_string(),
                     activate: Box::new(|_| {
-                        request_stop_recording();
+                        request_pause_recording("tray");
                     }),
                     ..Default::default()
                 }.into());
             }
             
-            // Add separator before Quit
-            items.push(ksni::MenuItem::Separator);
-            
             items.push(StandardItem {
-                label: "Quit".to_string(),
+                label: "🔖 Add Marker".to_string(),
                 activate: Box::new(|_| {
-                    std::process::exit(0);
+                    request_marker("tray");
                 }),
                 ..Default::default()
             }.into());
             
-            items
+            items.push(StandardItem {
+                label: "⏹ Stop Recording".to_string(),
+                activate: Box::new(|_| {
+                    request_stop_recording("tray");
+                }),
+                ..Default::default()
+            }.into());
         }
-    }
-    
-    fn toggle_window_visibility() {
-        if let Ok(guard) = WINDOW_VISIBLE.lock() {
-            if let Some(visible_ref) = guard.as_ref() {
-                if let Ok(mut visible) = visible_ref.lock() {
-                    *visible = !*visible;
-                    println!("Window visibility toggled to: {}", *visible);
-                }
-            }
+        
+        // Sources (applied to the next recording, so locked while recording)
+        items.push(ksni::MenuItem::Separator);
+        items.push(SubMenu {
+            label: "Microphone".to_string(),
+            enabled: !self.recording,
+            submenu: vec![self.source_radio_group(false, None)],
+            ..Default::default()
+        }.into());
+        items.push(SubMenu {
+            label: "System Audio Source".to_string(),
+            enabled: !self.recording,
+            submenu: vec![self.source_radio_group(true, Some("None"))],
+            ..Default::default()
+        }.into());
+        items.push(CheckmarkItem {
+            label: "Record System Audio".to_string(),
+            enabled: !self.recording,
+            checked: self.selected_loopback_index.is_some(),
+            activate: Box::new(|_| {
+                send_ui_message(UiMessage::ToggleSystemAudio);
+            }),
+            ..Default::default()
+        }.into());
+        
+        // Recent recordings and save folder
+        items.push(ksni::MenuItem::Separator);
+        let mut recent: Vec<ksni::MenuItem<Self>> = self
+            .recent_recordings
+            .iter()
+            .map(|path| {
+                let label = std::path::Path::new(path)
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_else(|| path.clone());
+                let path = path.clone();
+                StandardItem {
+                    label,
+                    activate: Box::new(move |_| open_with_default_app(&path)),
+                    ..Default::default()
+                }.into()
+            })
+            .collect();
+        if recent.is_empty() {
+            recent.push(StandardItem {
+                label: "No recordings yet".to_string(),
+                enabled: false,
+                ..Default::default()
+            }.into());
         }
-    }
-    
-    fn request_start_recording() {
-        if let Ok(guard) = RECORDING_STATE.lock() {
-            if let Some(state_ref) = guard.as_ref() {
-                if let Ok(mut state) = state_ref.lock() {
-                    state.start_requested = true;
-                    println!("Start recording requested from tray");
-                }
-            }
+        items.push(SubMenu {
+            label: "Recent Recordings".to_string(),
+            submenu: recent,
+            ..Default::default()
+        }.into());
+        // Uploads whose response had a link open it, the others their recording
+        if !self.recent_uploads.is_empty() {
+            let uploads = self
+                .recent_uploads
+                .iter()
+                .map(|upload| {
+                    let target = upload.response.url.clone().unwrap_or_else(|| upload.file_path.clone());
+                    let id = upload.response.id.as_ref().map(|id| format!(" · {}", id)).unwrap_or_default();
+                    StandardItem {
+                        label: format!("{} → {}{}", upload.file_name(), upload.destination, id),
+                        enabled: upload.response.url.is_some() || std::path::Path::new(&upload.file_path).exists(),
+                        activate: Box::new(move |_| open_with_default_app(&target)),
+                        ..Default::default()
+                    }.into()
+                })
+                .collect();
+            items.push(SubMenu {
+                label: "Recent Uploads".to_string(),
+                submenu: uploads,
+                ..Default::default()
+            }.into());
         }
-    }
-    
-    fn request_stop_recording() {
-        if let Ok(guard) = RECORDING_STATE.lock() {
-            if let Some(state_ref) = guard.as_ref() {
-                if let Ok(mut state) = state_ref.lock() {
-                    state.stop_requested = true;
-                    println!("Stop recording requested from tray");
-                }
-            }
+        let save_directory = self.save_directory.clone().unwrap_or_else(|| ".".to_string());
+        items.push(StandardItem {
+            label: "Open Save Folder".to_string(),
+            activate: Box::new(move |_| open_with_default_app(&save_directory)),
+            ..Default::default()
+        }.into());
+        if let Some(upload) = &self.upload {
+            items.push(StandardItem {
+                label: format!("Cancel Upload ({} %)", upload.percent()),
+                activate: Box::new(|_| upload_queue::cancel_upload()),
+                ..Default::default()
+            }.into());
         }
-    }
-    
-    fn request_pause_recording() {
-        if let Ok(guard) = RECORDING_STATE.lock() {
-            if let Some(state_ref) = guard.as_ref() {
-                if let Ok(mut state) = state_ref.lock() {
-                    state.pause_requested = true;
-                    println!("Pause toggle requested from tray");
-                }
-            }
+        if self.pending_uploads > 0 {
+            items.push(StandardItem {
+                label: format!("Pending Uploads ({})", self.pending_uploads),
+                activate: Box::new(|_| {
+                    send_ui_message(UiMessage::ShowUploadQueue);
+                }),
+                ..Default::default()
+            }.into());
         }
+        
+        // Add separator before Quit
+        items.push(ksni::MenuItem::Separator);
+        
+        items.push(StandardItem {
+            label: "Quit".to_string(),
+            activate: Box::new(|_| {
+                request_quit("tray", true);
+            }),
+            ..Default::default()
+        }.into());
+        
+        items
     }
+}
+
+fn start_tray_icon() {
+    use ksni::TrayService;
     
-    let service = TrayService::new(RecorderTray { recording: false, paused: false });
-    let handle = service.handle();
+    let service = TrayService::new(RecorderTray::default());
+    *TRAY_HANDLE.lock().unwrap() = Some(service.handle());
     service.spawn();
     
     println!("Tray icon started!");
-    
-    // Poll for state changes and update tray
-    let mut last_recording = false;
-    let mut last_paused = false;
-    
-    loop {
-        std::thread::sleep(Duration::from_millis(200));
-        
-        // Get current state from global
-        let (recording, paused) = {
-            if let Ok(guard) = RECORDING_STATE.lock() {
-                if let Some(state_ref) = guard.as_ref() {
-                    if let Ok(state) = state_ref.lock() {
-                        (state.recording, state.paused)
-                    } else {
-                        (false, false)
-                    }
-                } else {
-                    (false, false)
-                }
-            } else {
-                (false, false)
-            }
-        };
-        
-        // If state changed, update tray
-        if recording != last_recording || paused != last_paused {
-            handle.update(|tray: &mut RecorderTray| {
-                tray.recording = recording;
-                tray.paused = paused;
-            });
-            last_recording = recording;
-            last_paused = paused;
-        }
+}
+
+fn update_tray(f: impl FnOnce(&mut RecorderTray)) {
+    if let Some(handle) = TRAY_HANDLE.lock().unwrap().as_ref() {
+        handle.update(f);
     }
 }
 
-#[derive(Clone)]
-struct AudioSource {
-    name: String,
-    display_name: String,
-    is_monitor: bool,
+/// Send the source list, selections and save folder to the tray
+fn sync_tray_settings(state: &RecorderState) {
+    let sources = state.available_sources.clone();
+    let selected_mic_index = state.selected_mic_index;
+    let selected_loopback_index = state.selected_loopback_index;
+    let save_directory = state.save_directory.lock().unwrap().clone();
+    update_tray(move |tray| {
+        tray.sources = sources;
+        tray.selected_mic_index = selected_mic_index;
+        tray.selected_loopback_index = selected_loopback_index;
+        tray.recent_recordings = recent_recordings(save_directory.as_deref());
+        tray.recent_uploads = upload_history::recent(RECENT_RECORDINGS_COUNT);
+        tray.save_directory = save_directory;
+    });
 }
 
-struct RecorderState {
-    recording: bool,
-    paused: bool,
-    start_time: Option<Instant>,
-    elapsed: Duration,
-    input_stream: Option<cpal::Stream>,
-    output_stream: Option<cpal::Stream>,
-    input_level: Arc<Mutex<f32>>,
-    output_level: Arc<Mutex<f32>>,
-    input_samples: Arc<Mutex<Vec<f32>>>,
-    output_samples: Arc<Mutex<Vec<f32>>>,
-    waveform_history: Arc<Mutex<Vec<f32>>>,
-    input_waveform_history: Arc<Mutex<Vec<f32>>>,
-    output_waveform_history: Arc<Mutex<Vec<f32>>>,
-    sample_rate: u32,
-    channels: u16,
-    available_sources: Vec<AudioSource>,
-    selected_mic_index: usize,
-    selected_loopback_index: Option<usize>,
-    mic_gain: Arc<Mutex<f32>>,
-    save_directory: Arc<Mutex<Option<String>>>,
-    n8n_endpoint: Arc<Mutex<Option<String>>>,
+/// Re-list the recordings in the tray (after one was saved or deleted)
+fn refresh_tray_recordings() {
+    update_tray(|tray| {
+        tray.recent_recordings = recent_recordings(tray.save_directory.as_deref());
+        tray.recent_uploads = upload_history::recent(RECENT_RECORDINGS_COUNT);
+    });
+}
+
+/// Most recently modified .ogg files of the save directory
+fn recent_recordings(dir: Option<&str>) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(dir.unwrap_or(".")) else {
+        return Vec::new();
+    };
+    let mut files: Vec<(std::time::SystemTime, String)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "ogg"))
+        .filter_map(|entry| {
+            let modified = entry.metadata().ok()?.modified().ok()?;
+            Some((modified, entry.path().to_string_lossy().to_string()))
+        })
+        .collect();
+    files.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    files.into_iter().take(RECENT_RECORDINGS_COUNT).map(|(_, path)| path).collect()
+}
+
+fn open_with_default_app(path: &str) {
+    if let Err(e) = std::process::Command::new("xdg-open").arg(path).spawn() {
+        eprintln!("Failed to open {}: {}", path, e);
+    }
+}
+
+/// Push a recording state change to the shared snapshot, the tray and D-Bus listeners
+fn publish_recording_state(recording: bool, paused: bool) {
+    if let Some(snapshot) = RECORDING_STATE.lock().unwrap().as_ref() {
+        let mut snapshot = snapshot.lock().unwrap();
+        if (snapshot.recording, snapshot.paused) == (recording, paused) {
+            return;
+        }
+        snapshot.recording = recording;
+        snapshot.paused = paused;
+        if !recording {
+            snapshot.elapsed_secs = 0;
+        }
+    }
+    
+    update_tray(|tray| {
+        tray.recording = recording;
+        tray.paused = paused;
+        if !recording {
+            tray.elapsed_secs = 0;
+        }
+    });
+    control::emit_state_changed(recording, paused);
+}
+
+fn toggle_window_visibility() {
+    send_ui_message(UiMessage::ToggleWindow);
+}
+
+fn show_window() {
+    send_ui_message(UiMessage::ShowWindow);
+}
+
+fn request_start_recording(origin: &str) {
+    println!("Start recording requested from {}", origin);
+    send_ui_message(UiMessage::StartRecording);
+}
+
+fn request_stop_recording(origin: &str) {
+    println!("Stop recording requested from {}", origin);
+    send_ui_message(UiMessage::StopRecording);
+}
+
+fn request_toggle_recording(origin: &str) {
+    let recording = RECORDING_STATE
+        .lock()
+        .ok()
+        .and_then(|guard| guard.as_ref().map(|state| state.lock().unwrap().recording))
+        .unwrap_or(false);
+    if recording {
+        request_stop_recording(origin);
+    } else {
+        request_start_recording(origin);
+    }
+}
+
+fn request_pause_recording(origin: &str) {
+    println!("Pause toggle requested from {}", origin);
+    send_ui_message(UiMessage::TogglePause);
+}
+
+fn request_marker(origin: &str) {
+    println!("Marker requested from {}", origin);
+    send_ui_message(UiMessage::AddMarker);
+}
+
+fn request_quit(origin: &str, interactive: bool) {
+    println!("Quit requested from {}", origin);
+    send_ui_message(UiMessage::Quit { interactive });
+}
+
+#[derive(Clone)]
+struct AudioSource {
+    name: String,
+    display_name: String,
+    is_monitor: bool,
+}
+
+struct RecorderState {
+    recording: bool,
+    paused: bool,
+    start_time: Option<Instant>,
+    elapsed: Duration,
+    // Pause bookkeeping: the capture callbacks drop samples while paused
+    capture_paused: Arc<Mutex<bool>>,
+    paused_since: Option<Instant>,
+    paused_total: Duration,
+    markers: Vec<markers::Marker>,
+    input_stream: Option<cpal::Stream>,
+    output_stream: Option<cpal::Stream>,
+    input_meter: Arc<Mutex<meter::LevelMeter>>,
+    output_meter: Arc<Mutex<meter::LevelMeter>>,
+    input_samples: Arc<Mutex<Vec<f32>>>,
+    output_samples: Arc<Mutex<Vec<f32>>>,
+    waveform_history: Arc<Mutex<Vec<f32>>>,
+    input_waveform_history: Arc<Mutex<Vec<f32>>>,
+    output_waveform_history: Arc<Mutex<Vec<f32>>>,
+    input_spectrum: Arc<Mutex<spectrum::SpectrumTap>>,
+    output_spectrum: Arc<Mutex<spectrum::SpectrumTap>>,
+    sample_rate: u32,
+    channels: u16,
+    available_sources: Vec<AudioSource>,
+    selected_mic_index: Option<usize>,
+    selected_loopback_index: Option<usize>,
+    mic_gain: Arc<Mutex<f32>>,
+    agc: Arc<Mutex<agc::AutoGain>>,
+    mic_chain: Arc<Mutex<denoise::MicChainSettings>>,
+    echo_cancellation: Arc<Mutex<bool>>,
+    // One-shot output file for the next recording (used by the command line)
+    output_path: Option<String>,
+    save_directory: Arc<Mutex<Option<String>>>,
+    upload_destinations: Arc<Mutex<Vec<UploadDestination>>>,
+    default_destination: Arc<Mutex<Option<String>>>,
     n8n_enabled: Arc<Mutex<bool>>,
     save_locally: Arc<Mutex<bool>>,
+    auto_upload: Arc<Mutex<AutoUpload>>,
+    last_speakers: Arc<Mutex<std::collections::BTreeMap<String, SpeakerCounts>>>,
+    secrets_by_destination: bool,
+    loudness_normalization: Arc<Mutex<bool>>,
+    loudness_target_lufs: Arc<Mutex<f32>>,
+    shortcuts: Arc<Mutex<shortcuts::ShortcutBindings>>,
+    quit_action: Arc<Mutex<QuitAction>>,
 }
 
 // Helper function to draw waveform bars (used by both mic and system audio visualizations)
@@ -376,6 +1051,128 @@ fn draw_waveform_bars(
     }
 }
 
+// Helper function to draw a dBFS level meter: RMS bar with colour scale,
+// peak line and peak-hold tick
+fn draw_level_meter(
+    cr: &gtk4::cairo::Context,
+    meter: &meter::LevelMeter,
+    width: i32,
+    height: i32,
+) {
+    let width = width as f64;
+    let height = height as f64;
+    let floor = meter::METER_FLOOR_DB as f64;
+    let x_for_db = |db: f32| ((db as f64 - floor) / -floor).clamp(0.0, 1.0) * width;
+    
+    // Background
+    cr.set_source_rgb(0.95, 0.96, 0.96);
+    let _ = cr.rectangle(0.0, 0.0, width, height);
+    let _ = cr.fill();
+    
+    // RMS bar, coloured by zone: green up to -18 dBFS, yellow up to -6, red above
+    let zones = [
+        (floor as f32, -18.0, (0.13, 0.77, 0.37)),
+        (-18.0, -6.0, (0.92, 0.70, 0.03)),
+        (-6.0, 0.0, (0.94, 0.27, 0.27)),
+    ];
+    for (zone_start, zone_end, color) in zones {
+        if meter.rms_db <= zone_start {
+            break;
+        }
+        let x0 = x_for_db(zone_start);
+        let x1 = x_for_db(meter.rms_db.min(zone_end));
+        cr.set_source_rgb(color.0, color.1, color.2);
+        let _ = cr.rectangle(x0, 0.0, x1 - x0, height);
+        let _ = cr.fill();
+    }
+    
+    // Scale ticks every 12 dB
+    cr.set_source_rgba(0.0, 0.0, 0.0, 0.25);
+    for db in [-48.0, -36.0, -24.0, -12.0] {
+        let _ = cr.rectangle(x_for_db(db).round(), height - 3.0, 1.0, 3.0);
+    }
+    let _ = cr.fill();
+    
+    // Instant peak
+    if meter.peak_db > meter::METER_FLOOR_DB {
+        cr.set_source_rgba(0.12, 0.16, 0.22, 0.6);
+        let _ = cr.rectangle(x_for_db(meter.peak_db) - 1.0, 0.0, 1.0, height);
+        let _ = cr.fill();
+    }
+    
+    // Peak hold
+    if meter.peak_hold_db > meter::METER_FLOOR_DB {
+        cr.set_source_rgb(0.12, 0.16, 0.22);
+        let _ = cr.rectangle(x_for_db(meter.peak_hold_db) - 2.0, 0.0, 2.0, height);
+        let _ = cr.fill();
+    }
+}
+
+// Helper function to draw the live spectrum of one source (log frequency axis)
+fn draw_spectrum(
+    cr: &gtk4::cairo::Context,
+    view: &spectrum::SpectrumView,
+    width: i32,
+    height: i32,
+    color: (f64, f64, f64),
+) {
+    let width = width as f64;
+    let height = height as f64;
+    
+    cr.set_source_rgb(0.95, 0.96, 0.96);
+    let _ = cr.rectangle(0.0, 0.0, width, height);
+    let _ = cr.fill();
+    
+    let band_width = width / spectrum::NUM_BANDS as f64;
+    cr.set_source_rgb(color.0, color.1, color.2);
+    for (i, &db) in view.bands.iter().enumerate() {
+        let level = ((db - spectrum::MIN_DB) / -spectrum::MIN_DB).clamp(0.0, 1.0) as f64;
+        let bar_height = level * height;
+        let _ = cr.rectangle(i as f64 * band_width, height - bar_height, (band_width - 1.0).max(1.0), bar_height);
+    }
+    let _ = cr.fill();
+}
+
+// Helper function to draw the scrolling spectrogram of one source (newest column on the right)
+fn draw_spectrogram(
+    cr: &gtk4::cairo::Context,
+    view: &spectrum::SpectrumView,
+    width: i32,
+    height: i32,
+) {
+    use gtk4::cairo::{Filter, Format, ImageSurface, SurfacePattern};
+    
+    let columns = spectrum::SPECTROGRAM_COLUMNS;
+    let bands = spectrum::NUM_BANDS;
+    let Ok(mut surface) = ImageSurface::create(Format::Rgb24, columns as i32, bands as i32) else {
+        return;
+    };
+    let stride = surface.stride() as usize;
+    
+    if let Ok(mut data) = surface.data() {
+        let background = spectrum::heat_color(spectrum::MIN_DB);
+        let offset = columns - view.spectrogram.len();
+        for x in 0..columns {
+            let column = x.checked_sub(offset).and_then(|i| view.spectrogram.get(i));
+            for band in 0..bands {
+                let (r, g, b) = column
+                    .map(|c| spectrum::heat_color(c[band]))
+                    .unwrap_or(background);
+                // Rgb24 is stored as native-endian 0x00RRGGBB
+                let pixel = (((r as u32) << 16) | ((g as u32) << 8) | b as u32).to_ne_bytes();
+                let index = (bands - 1 - band) * stride + x * 4;
+                data[index..index + 4].copy_from_slice(&pixel);
+            }
+        }
+    }
+    
+    let pattern = SurfacePattern::create(&surface);
+    pattern.set_filter(Filter::Fast);
+    cr.scale(width as f64 / columns as f64, height as f64 / bands as f64);
+    let _ = cr.set_source(&pattern);
+    let _ = cr.paint();
+}
+
 // Helper function to find the pulse audio device
 fn find_pulse_device() -> Option<cpal::Device> {
     let host = cpal::default_host();
@@ -407,45 +1204,71 @@ fn convert_i32_to_f32(data: &[i32]) -> Vec<f32> {
 }
 
 // Helper function to process microphone samples (handles any sample format after conversion to f32)
+#[allow(clippy::too_many_arguments)]
 fn process_mic_samples(
     data: &[f32],
+    paused: &Arc<Mutex<bool>>,
     mic_gain: &Arc<Mutex<f32>>,
-    input_level: &Arc<Mutex<f32>>,
+    agc: &Arc<Mutex<agc::AutoGain>>,
+    input_meter: &Arc<Mutex<meter::LevelMeter>>,
     input_waveform_history: &Arc<Mutex<Vec<f32>>>,
+    input_spectrum: &Arc<Mutex<spectrum::SpectrumTap>>,
     input_samples: &Arc<Mutex<Vec<f32>>>,
 ) {
-    let gain = *mic_gain.lock().unwrap();
-    let gained_data: Vec<f32> = data.iter()
-        .map(|&s| (s * gain).clamp(-1.0, 1.0))
+    let fixed_gain = *mic_gain.lock().unwrap();
+    
+    // Ramp from the previous gain to the new one across the block to avoid zipper noise
+    let (start_gain, end_gain) = {
+        let mut agc = agc.lock().unwrap();
+        let start = agc.effective_gain();
+        let end = agc.gain_for_block(data, fixed_gain);
+        (start, end)
+    };
+    let step = (end_gain - start_gain) / data.len().max(1) as f32;
+    let mut gained_data: Vec<f32> = data.iter()
+        .enumerate()
+        .map(|(i, &s)| s * (start_gain + step * i as f32))
         .collect();
     
+    // Meter before clamping so that overs light the clip indicator
+    input_meter.lock().unwrap().update(&gained_data);
+    gained_data.iter_mut().for_each(|s| *s = s.clamp(-1.0, 1.0));
+    
     let sum: f32 = gained_data.iter().map(|&s| s * s).sum();
     let rms = (sum / gained_data.len().max(1) as f32).sqrt();
     let mic_level = (rms * 5.0).min(1.0);
-    *input_level.lock().unwrap() = mic_level;
     
     // Update input waveform history
     let mut history = input_waveform_history.lock().unwrap();
     history.remove(0);
     history.push(mic_level);
+    drop(history);
     
-    input_samples.lock().unwrap().extend_from_slice(&gained_data);
+    input_spectrum.lock().unwrap().push(&gained_data);
+    // Meters stay live while paused, but the audio is not kept
+    if !*paused.lock().unwrap() {
+        input_samples.lock().unwrap().extend_from_slice(&gained_data);
+    }
 }
 
 // Helper function to process loopback samples (handles any sample format after conversion to f32)
+#[allow(clippy::too_many_arguments)]
 fn process_loopback_samples(
     data: &[f32],
+    paused: &Arc<Mutex<bool>>,
     source_channels: u16,
     source_sample_rate: u32,
     target_sample_rate: u32,
-    output_level: &Arc<Mutex<f32>>,
+    output_meter: &Arc<Mutex<meter::LevelMeter>>,
     output_waveform_history: &Arc<Mutex<Vec<f32>>>,
+    output_spectrum: &Arc<Mutex<spectrum::SpectrumTap>>,
     output_samples: &Arc<Mutex<Vec<f32>>>,
 ) {
+    output_meter.lock().unwrap().update(data);
+    
     let sum: f32 = data.iter().map(|&s| s * s).sum();
     let rms = (sum / data.len().max(1) as f32).sqrt();
     let loopback_level = (rms * 5.0).min(1.0);
-    *output_level.lock().unwrap() = loopback_level;
     
     // Update output waveform history
     let mut history = output_waveform_history.lock().unwrap();
@@ -477,7 +1300,10 @@ fn process_loopback_samples(
         mono
     };
     
-    output_samples.lock().unwrap().extend_from_slice(&resampled);
+    output_spectrum.lock().unwrap().push(&resampled);
+    if !*paused.lock().unwrap() {
+        output_samples.lock().unwrap().extend_from_slice(&resampled);
+    }
 }
 
 impl RecorderState {
@@ -489,15 +1315,14 @@ impl RecorderState {
         
         let selected_mic_index = config
             .as_ref()
-            .map(|c| c.selected_mic_index)
+            .and_then(|c| c.selected_mic_index)
             .filter(|&idx| idx < available_sources.len() && !available_sources[idx].is_monitor)
             .or_else(|| {
                 available_sources
                     .iter()
                     .position(|s| !s.is_monitor && (s.name == "pulse" || s.name == "pipewire"))
                     .or_else(|| available_sources.iter().position(|s| !s.is_monitor))
-            })
-            .unwrap_or(0);
+            });
         
         let selected_loopback_index = config
             .as_ref()
@@ -510,13 +1335,40 @@ impl RecorderState {
             .map(|c| c.mic_gain)
             .unwrap_or(1.0);
         
+        let agc = agc::AutoGain::new(
+            config.as_ref().map(|c| c.agc_enabled).unwrap_or(false),
+            config.as_ref().map(|c| c.agc_target_dbfs).unwrap_or_else(default_agc_target),
+            config.as_ref().map(|c| c.agc_attack_ms).unwrap_or_else(default_agc_attack),
+            config.as_ref().map(|c| c.agc_release_ms).unwrap_or_else(default_agc_release),
+            AGC_MAX_GAIN_DB,
+        );
+        
+        let mic_chain = denoise::MicChainSettings {
+            highpass_enabled: config.as_ref().map(|c| c.highpass_enabled).unwrap_or(false),
+            highpass_cutoff_hz: config.as_ref().map(|c| c.highpass_cutoff_hz).unwrap_or_else(default_highpass_cutoff),
+            suppression_enabled: config.as_ref().map(|c| c.noise_suppression_enabled).unwrap_or(false),
+            suppression_db: config.as_ref().map(|c| c.noise_suppression_db).unwrap_or_else(default_noise_suppression),
+            gate_enabled: config.as_ref().map(|c| c.noise_gate_enabled).unwrap_or(false),
+            gate_threshold_dbfs: config.as_ref().map(|c| c.noise_gate_threshold_dbfs).unwrap_or_else(default_noise_gate_threshold),
+        };
+        
+        let echo_cancellation = config
+            .as_ref()
+            .map(|c| c.echo_cancellation)
+            .unwrap_or(false);
+        
         let save_directory = config
             .as_ref()
             .and_then(|c| c.save_directory.clone());
         
-        let n8n_endpoint = config
+        let upload_destinations = config
+            .as_ref()
+            .map(|c| c.upload_destinations.clone())
+            .unwrap_or_default();
+        
+        let default_destination = config
             .as_ref()
-            .and_then(|c| c.n8n_endpoint.clone());
+            .and_then(|c| c.default_destination.clone());
         
         let n8n_enabled = config
             .as_ref()
@@ -528,43 +1380,126 @@ impl RecorderState {
             .map(|c| c.save_locally)
             .unwrap_or(true);
         
+        let auto_upload = AutoUpload {
+            enabled: config.as_ref().is_some_and(|c| c.auto_upload),
+            countdown_enabled: config.as_ref().is_some_and(|c| c.auto_upload_countdown),
+            countdown_secs: config
+                .as_ref()
+                .map(|c| c.auto_upload_countdown_secs)
+                .unwrap_or_else(default_auto_upload_countdown),
+        };
+        
+        let last_speakers = config
+            .as_ref()
+            .map(|c| c.last_speakers.clone())
+            .unwrap_or_default();
+        
+        // Without a config there is no secret of an older version to move
+        let secrets_by_destination = config.as_ref().map(|c| c.secrets_by_destination).unwrap_or(true);
+        
+        let loudness_normalization = config
+            .as_ref()
+            .map(|c| c.loudness_normalization)
+            .unwrap_or(false);
+        
+        let loudness_target_lufs = config
+            .as_ref()
+            .map(|c| c.loudness_target_lufs)
+            .unwrap_or_else(default_loudness_target);
+        
+        let shortcuts = shortcuts::ShortcutBindings {
+            toggle_recording: config.as_ref().map(|c| c.shortcut_toggle_recording.clone()).unwrap_or_else(default_shortcut_toggle_recording),
+            pause: config.as_ref().map(|c| c.shortcut_pause.clone()).unwrap_or_else(default_shortcut_pause),
+            marker: config.as_ref().map(|c| c.shortcut_marker.clone()).unwrap_or_else(default_shortcut_marker),
+        };
+        
+        let quit_action = config.as_ref().map(|c| c.quit_action).unwrap_or_default();
+        
         Self {
             recording: false,
             paused: false,
             start_time: None,
             elapsed: Duration::default(),
+            capture_paused: Arc::new(Mutex::new(false)),
+            paused_since: None,
+            paused_total: Duration::default(),
+            markers: Vec::new(),
             input_stream: None,
             output_stream: None,
-            input_level: Arc::new(Mutex::new(0.0)),
-            output_level: Arc::new(Mutex::new(0.0)),
+            input_meter: Arc::new(Mutex::new(meter::LevelMeter::default())),
+            output_meter: Arc::new(Mutex::new(meter::LevelMeter::default())),
             input_samples: Arc::new(Mutex::new(Vec::new())),
             output_samples: Arc::new(Mutex::new(Vec::new())),
             waveform_history: Arc::new(Mutex::new(vec![0.0; 60])),
             input_waveform_history: Arc::new(Mutex::new(vec![0.0; 60])),
             output_waveform_history: Arc::new(Mutex::new(vec![0.0; 60])),
+            input_spectrum: Arc::new(Mutex::new(spectrum::SpectrumTap::default())),
+            output_spectrum: Arc::new(Mutex::new(spectrum::SpectrumTap::default())),
             sample_rate: 48000,
             channels: 1,
             available_sources,
             selected_mic_index,
             selected_loopback_index,
             mic_gain: Arc::new(Mutex::new(mic_gain)),
+            agc: Arc::new(Mutex::new(agc)),
+            mic_chain: Arc::new(Mutex::new(mic_chain)),
+            echo_cancellation: Arc::new(Mutex::new(echo_cancellation)),
+            output_path: None,
             save_directory: Arc::new(Mutex::new(save_directory)),
-            n8n_endpoint: Arc::new(Mutex::new(n8n_endpoint)),
+            upload_destinations: Arc::new(Mutex::new(upload_destinations)),
+            default_destination: Arc::new(Mutex::new(default_destination)),
             n8n_enabled: Arc::new(Mutex::new(n8n_enabled)),
             save_locally: Arc::new(Mutex::new(save_locally)),
+            auto_upload: Arc::new(Mutex::new(auto_upload)),
+            last_speakers: Arc::new(Mutex::new(last_speakers)),
+            secrets_by_destination,
+            loudness_normalization: Arc::new(Mutex::new(loudness_normalization)),
+            loudness_target_lufs: Arc::new(Mutex::new(loudness_target_lufs)),
+            shortcuts: Arc::new(Mutex::new(shortcuts)),
+            quit_action: Arc::new(Mutex::new(quit_action)),
         }
     }
     
     fn save_config(&self) {
+        let agc = self.agc.lock().unwrap();
+        let mic_chain = *self.mic_chain.lock().unwrap();
+        let shortcuts = self.shortcuts.lock().unwrap().clone();
+        let auto_upload = *self.auto_upload.lock().unwrap();
         let config = Config {
             selected_mic_index: self.selected_mic_index,
             selected_loopback_index: self.selected_loopback_index,
             mic_gain: *self.mic_gain.lock().unwrap(),
             save_directory: self.save_directory.lock().unwrap().clone(),
-            n8n_endpoint: self.n8n_endpoint.lock().unwrap().clone(),
+            n8n_endpoint: None,
             n8n_enabled: *self.n8n_enabled.lock().unwrap(),
             save_locally: *self.save_locally.lock().unwrap(),
+            loudness_normalization: *self.loudness_normalization.lock().unwrap(),
+            loudness_target_lufs: *self.loudness_target_lufs.lock().unwrap(),
+            agc_enabled: agc.enabled,
+            agc_target_dbfs: agc.target_dbfs,
+            agc_attack_ms: agc.attack_ms,
+            agc_release_ms: agc.release_ms,
+            highpass_enabled: mic_chain.highpass_enabled,
+            highpass_cutoff_hz: mic_chain.highpass_cutoff_hz,
+            noise_suppression_enabled: mic_chain.suppression_enabled,
+            noise_suppression_db: mic_chain.suppression_db,
+            noise_gate_enabled: mic_chain.gate_enabled,
+            noise_gate_threshold_dbfs: mic_chain.gate_threshold_dbfs,
+            echo_cancellation: *self.echo_cancellation.lock().unwrap(),
+            shortcut_toggle_recording: shortcuts.toggle_recording,
+            shortcut_pause: shortcuts.pause,
+            shortcut_marker: shortcuts.marker,
+            quit_action: *self.quit_action.lock().unwrap(),
+            upload_auth: Default::default(),
+            upload_destinations: self.upload_destinations.lock().unwrap().clone(),
+            default_destination: self.default_destination.lock().unwrap().clone(),
+            auto_upload: auto_upload.enabled,
+            auto_upload_countdown: auto_upload.countdown_enabled,
+            auto_upload_countdown_secs: auto_upload.countdown_secs,
+            last_speakers: self.last_speakers.lock().unwrap().clone(),
+            secrets_by_destination: self.secrets_by_destination,
         };
+        drop(agc);
         
         if let Err(e) = config.save() {
             eprintln!("Failed to save config: {}", e);
@@ -616,9 +1551,18 @@ impl RecorderState {
     fn start_recording(&mut self) {
         self.start_time = Some(Instant::now());
         self.elapsed = Duration::default();
+        self.set_paused(false);
+        self.paused_total = Duration::default();
+        self.markers.clear();
         
-        let mic_source_name = self.available_sources
-            .get(self.selected_mic_index)
+        for meter in [&self.input_meter, &self.output_meter] {
+            let mut meter = meter.lock().unwrap();
+            meter.reset();
+            meter.reset_clip();
+        }
+        
+        let mic_source_name = self.selected_mic_index
+            .and_then(|idx| self.available_sources.get(idx))
             .map(|s| s.name.clone());
         
         if let Some(mic_name) = mic_source_name {
@@ -635,10 +1579,20 @@ impl RecorderState {
                     println!("Mic config: {} Hz, {} channels, format: {:?}", 
                              self.sample_rate, self.channels, sample_format);
                     
-                    let input_level = Arc::clone(&self.input_level);
+                    let input_meter = Arc::clone(&self.input_meter);
                     let input_samples = Arc::clone(&self.input_samples);
                     let input_waveform_history = Arc::clone(&self.input_waveform_history);
+                    let input_spectrum = Arc::clone(&self.input_spectrum);
+                    self.input_spectrum.lock().unwrap().configure(self.sample_rate, self.channels);
                     let mic_gain = Arc::clone(&self.mic_gain);
+                    let agc = Arc::clone(&self.agc);
+                    let paused = Arc::clone(&self.capture_paused);
+                    
+                    // AGC starts adapting from the fixed gain at every recording
+                    self.agc.lock().unwrap().reset(
+                        self.sample_rate * self.channels as u32,
+                        *self.mic_gain.lock().unwrap(),
+                    );
                     
                     let stream_config: cpal::StreamConfig = input_config.clone().into();
                     
@@ -648,7 +1602,7 @@ impl RecorderState {
                             input_device.build_input_stream(
                                 &stream_config,
                                 move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                                    process_mic_samples(data, &mic_gain, &input_level, &input_waveform_history, &input_samples);
+                                    process_mic_samples(data, &paused, &mic_gain, &agc, &input_meter, &input_waveform_history, &input_spectrum, &input_samples);
                                 },
                                 |err| eprintln!("Mic error: {}", err),
                                 None,
@@ -659,7 +1613,7 @@ impl RecorderState {
                                 &stream_config,
                                 move |data: &[i16], _: &cpal::InputCallbackInfo| {
                                     let float_data = convert_i16_to_f32(data);
-                                    process_mic_samples(&float_data, &mic_gain, &input_level, &input_waveform_history, &input_samples);
+                                    process_mic_samples(&float_data, &paused, &mic_gain, &agc, &input_meter, &input_waveform_history, &input_spectrum, &input_samples);
                                 },
                                 |err| eprintln!("Mic error: {}", err),
                                 None,
@@ -670,7 +1624,7 @@ impl RecorderState {
                                 &stream_config,
                                 move |data: &[i32], _: &cpal::InputCallbackInfo| {
                                     let float_data = convert_i32_to_f32(data);
-                                    process_mic_samples(&float_data, &mic_gain, &input_level, &input_waveform_history, &input_samples);
+                                    process_mic_samples(&float_data, &paused, &mic_gain, &agc, &input_meter, &input_waveform_history, &input_spectrum, &input_samples);
                                 },
                                 |err| eprintln!("Mic error: {}", err),
                                 None,
@@ -713,10 +1667,14 @@ impl RecorderState {
             
             if let Some(loopback_device) = pulse_device {
                 if let Ok(loopback_config) = loopback_device.default_input_config() {
-                    let output_level = Arc::clone(&self.output_level);
+                    let output_meter = Arc::clone(&self.output_meter);
                     let output_samples = Arc::clone(&self.output_samples);
                     let output_waveform_history = Arc::clone(&self.output_waveform_history);
+                    let output_spectrum = Arc::clone(&self.output_spectrum);
+                    let paused = Arc::clone(&self.capture_paused);
                     let target_sample_rate = self.sample_rate;
+                    // Loopback samples reach the tap already downmixed and resampled
+                    self.output_spectrum.lock().unwrap().configure(target_sample_rate, 1);
                     let source_sample_rate = loopback_config.sample_rate().0;
                     let source_channels = loopback_config.channels();
                     let sample_format = loopback_config.sample_format();
@@ -732,7 +1690,7 @@ impl RecorderState {
                             loopback_device.build_input_stream(
                                 &stream_config,
                                 move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                                    process_loopback_samples(data, source_channels, source_sample_rate, target_sample_rate, &output_level, &output_waveform_history, &output_samples);
+                                    process_loopback_samples(data, &paused, source_channels, source_sample_rate, target_sample_rate, &output_meter, &output_waveform_history, &output_spectrum, &output_samples);
                                 },
                                 |err| eprintln!("Loopback error: {}", err),
                                 None,
@@ -743,7 +1701,7 @@ impl RecorderState {
                                 &stream_config,
                                 move |data: &[i16], _: &cpal::InputCallbackInfo| {
                                     let float_data = convert_i16_to_f32(data);
-                                    process_loopback_samples(&float_data, source_channels, source_sample_rate, target_sample_rate, &output_level, &output_waveform_history, &output_samples);
+                                    process_loopback_samples(&float_data, &paused, source_channels, source_sample_rate, target_sample_rate, &output_meter, &output_waveform_history, &output_spectrum, &output_samples);
                                 },
                                 |err| eprintln!("Loopback error: {}", err),
                                 None,
@@ -754,7 +1712,7 @@ impl RecorderState {
                                 &stream_config,
                                 move |data: &[i32], _: &cpal::InputCallbackInfo| {
                                     let float_data = convert_i32_to_f32(data);
-                                    process_loopback_samples(&float_data, source_channels, source_sample_rate, target_sample_rate, &output_level, &output_waveform_history, &output_samples);
+                                    process_loopback_samples(&float_data, &paused, source_channels, source_sample_rate, target_sample_rate, &output_meter, &output_waveform_history, &output_spectrum, &output_samples);
                                 },
                                 |err| eprintln!("Loopback error: {}", err),
                                 None,
@@ -789,13 +1747,64 @@ impl RecorderState {
         println!("Recording started");
     }
     
-    fn stop_recording(&mut self) {
+    fn set_paused(&mut self, paused: bool) {
+        if paused && !self.paused {
+            self.paused_since = Some(Instant::now());
+        } else if !paused {
+            if let Some(since) = self.paused_since.take() {
+                self.paused_total += since.elapsed();
+            }
+        }
+        self.paused = paused;
+        *self.capture_paused.lock().unwrap() = paused;
+    }
+    
+    /// Time recorded so far, paused time excluded
+    fn recording_offset(&self) -> Duration {
+        let Some(start) = self.start_time else {
+            return Duration::default();
+        };
+        let current_pause = self.paused_since.map(|since| since.elapsed()).unwrap_or_default();
+        start.elapsed().saturating_sub(self.paused_total + current_pause)
+    }
+    
+    /// Add a marker at the current offset, returning its index
+    fn add_marker(&mut self, note: &str) -> Option<usize> {
+        if !self.recording {
+            return None;
+        }
+        let offset = self.recording_offset();
+        println!("Marker at {}", format_duration(offset.as_secs()));
+        self.markers.push(markers::Marker {
+            offset_seconds: offset.as_secs_f64(),
+            note: note.to_string(),
+        });
+        Some(self.markers.len() - 1)
+    }
+    
+    /// Close the capture streams: once they are dropped, no callback adds samples
+    fn stop_streams(&mut self) {
         if let Some(stream) = self.input_stream.take() {
             drop(stream);
         }
         if let Some(stream) = self.output_stream.take() {
             drop(stream);
         }
+    }
+    
+    /// Stop capturing and throw away the audio and markers captured so far, so the
+    /// next stop saves nothing
+    fn discard_captured_audio(&mut self) {
+        self.stop_streams();
+        self.input_samples.lock().unwrap().clear();
+        self.output_samples.lock().unwrap().clear();
+        self.markers.clear();
+    }
+    
+    /// Stop capturing and hand the audio to a background encoding thread.
+    /// Returns the thread handle (yielding the saved file path), or None if nothing was captured.
+    fn stop_recording(&mut self) -> Option<std::thread::JoinHandle<Option<String>>> {
+        self.stop_streams();
         
         let mic_samples: Vec<f32> = {
             let mut samples = self.input_samples.lock().unwrap();
@@ -820,22 +1829,16 @@ impl RecorderState {
                 .collect()
         };
         
-        let max_len = mic_mono.len().max(system_samples.len());
-        let mixed_samples: Vec<f32> = (0..max_len)
-            .map(|i| {
-                let mic = mic_mono.get(i).copied().unwrap_or(0.0);
-                let sys = system_samples.get(i).copied().unwrap_or(0.0);
-                ((mic + sys) * 0.7).clamp(-1.0, 1.0)
-            })
-            .collect();
-        
-        if !mixed_samples.is_empty() {
+        let mut encode_thread = None;
+        if !mic_mono.is_empty() || !system_samples.is_empty() {
             let timestamp = Local::now().format("%Y%m%d_%H%M%S");
             let filename = format!("recording_{}.ogg", timestamp);
             
             // Get save directory from config or use current directory
             let save_dir = self.save_directory.lock().unwrap().clone();
-            let file_path = if let Some(dir) = save_dir {
+            let file_path = if let Some(path) = self.output_path.take() {
+                path
+            } else if let Some(dir) = save_dir {
                 // Create directory if it doesn't exist
                 if let Err(e) = std::fs::create_dir_all(&dir) {
                     eprintln!("Failed to create directory {}: {}", dir, e);
@@ -849,39 +1852,131 @@ impl RecorderState {
             
             // Collect N8N settings before spawning thread
             let n8n_enabled = *self.n8n_enabled.lock().unwrap();
-            let n8n_endpoint = self.n8n_endpoint.lock().unwrap().clone();
+            let upload_destinations = self.upload_destinations.lock().unwrap().clone();
+            let default_destination = self.default_destination.lock().unwrap().clone();
             let save_locally = *self.save_locally.lock().unwrap();
+            let auto_upload = *self.auto_upload.lock().unwrap();
+            let last_speakers = self.last_speakers.lock().unwrap().clone();
             let source_sample_rate = self.sample_rate;
+            let loudness_target = if *self.loudness_normalization.lock().unwrap() {
+                Some(*self.loudness_target_lufs.lock().unwrap())
+            } else {
+                None
+            };
+            let mic_chain = *self.mic_chain.lock().unwrap();
+            let echo_cancellation = *self.echo_cancellation.lock().unwrap();
+            let markers = std::mem::take(&mut self.markers);
             
-            // Spawn encoding work on a background thread to avoid blocking the UI
-            std::thread::spawn(move || {
+            // Spawn mic clean-up, mixing and encoding on a background thread to avoid blocking the UI
+            let task = BackgroundTask::start();
+            encode_thread = Some(std::thread::spawn(move || {
+                let _task = task;
+                // Echo cancellation first: the later stages are non-linear
+                let mic_mono = if echo_cancellation && !system_samples.is_empty() && !mic_mono.is_empty() {
+                    aec::cancel_echo(&mic_mono, &system_samples, source_sample_rate)
+                } else {
+                    mic_mono
+                };
+                let mic_processed = if mic_chain.any_enabled() {
+                    println!("Processing mic track: {:?}", mic_chain);
+                    denoise::process_mic_track(&mic_mono, source_sample_rate, &mic_chain)
+                } else {
+                    mic_mono
+                };
+                let mixed_samples = mix_tracks(&mic_processed, &system_samples);
+                
                 encode_and_save_recording(
                     mixed_samples,
                     source_sample_rate,
                     file_path,
                     n8n_enabled,
-                    n8n_endpoint,
+                    upload_destinations,
+                    default_destination,
                     save_locally,
-                );
-            });
+                    auto_upload,
+                    last_speakers,
+                    loudness_target,
+                    markers,
+                )
+            }));
         }
         
         self.recording = false;
-        self.paused = false;
+        self.set_paused(false);
         self.elapsed = Duration::default();
-        *self.input_level.lock().unwrap() = 0.0;
-        *self.output_level.lock().unwrap() = 0.0;
+        self.markers.clear();
+        self.input_meter.lock().unwrap().reset();
+        self.output_meter.lock().unwrap().reset();
         
         // Reset all waveform histories
         self.waveform_history.lock().unwrap().iter_mut().for_each(|v| *v = 0.0);
         self.input_waveform_history.lock().unwrap().iter_mut().for_each(|v| *v = 0.0);
         self.output_waveform_history.lock().unwrap().iter_mut().for_each(|v| *v = 0.0);
+        
+        encode_thread
     }
 }
 
+/// Mix the mono mic track with the system track
+fn mix_tracks(mic: &[f32], system: &[f32]) -> Vec<f32> {
+    let max_len = mic.len().max(system.len());
+    (0..max_len)
+        .map(|i| {
+            let mic = mic.get(i).copied().unwrap_or(0.0);
+            let sys = system.get(i).copied().unwrap_or(0.0);
+            // No clamping here: peaks are handled by the soft limiter at export
+            (mic + sys) * 0.7
+        })
+        .collect()
+}
+
 // Target sample rate for export (16kHz for smaller file size, good for speech)
 const EXPORT_SAMPLE_RATE: u32 = 16000;
 
+// Average bitrate of the export (16kHz mono Vorbis at the encoder's default quality),
+// used to estimate file size and remaining recording time
+const ESTIMATED_EXPORT_BITRATE: u64 = 40_000;
+
+// How often free disk space is re-read while the window updates
+const DISK_SPACE_REFRESH: Duration = Duration::from_secs(5);
+
+/// Format a duration as HH:MM:SS (hours are not wrapped)
+fn format_duration(total_secs: u64) -> String {
+    format!("{:02}:{:02}:{:02}", total_secs / 3600, (total_secs / 60) % 60, total_secs % 60)
+}
+
+/// Human readable size (Ko / Mo / Go)
+fn format_size(bytes: u64) -> String {
+    let bytes = bytes as f64;
+    if bytes >= 1e9 {
+        format!("{:.1} Go", bytes / 1e9)
+    } else if bytes >= 1e6 {
+        format!("{:.1} Mo", bytes / 1e6)
+    } else {
+        format!("{:.0} Ko", bytes / 1e3)
+    }
+}
+
+/// Estimated size of the exported file for a recording of `secs` seconds
+fn estimated_export_size(secs: u64) -> u64 {
+    secs * ESTIMATED_EXPORT_BITRATE / 8
+}
+
+/// Read the free space (in bytes) on the filesystem holding `dir` in a gio worker
+/// thread, so a slow or hung network mount cannot freeze the window. `done` runs on
+/// the main loop.
+fn query_disk_space(dir: &str, done: impl FnOnce(Option<u64>) + 'static) {
+    const FREE: &str = "filesystem::free";
+    gio::File::for_path(dir).query_filesystem_info_async(
+        FREE,
+        glib::Priority::LOW,
+        gio::Cancellable::NONE,
+        move |result| {
+            done(result.ok().filter(|info| info.has_attribute(FREE)).map(|info| info.attribute_uint64(FREE)))
+        },
+    );
+}
+
 /// Resample audio from source_rate to target_rate using linear interpolation
 fn resample_audio(samples: &[f32], source_rate: u32, target_rate: u32) -> Vec<f32> {
     if source_rate == target_rate {
@@ -906,15 +2001,23 @@ fn resample_audio(samples: &[f32], source_rate: u32, target_rate: u32) -> Vec<f3
         .collect()
 }
 
-/// Encode and save recording in a background thread (16kHz mono OGG)
+/// Encode and save recording in a background thread (16kHz mono OGG).
+/// `loudness_target` enables EBU R128 normalization to the given LUFS value.
+/// Returns the path of the written file.
+#[allow(clippy::too_many_arguments)]
 fn encode_and_save_recording(
     samples: Vec<f32>,
     source_sample_rate: u32,
     file_path: String,
     n8n_enabled: bool,
-    n8n_endpoint: Option<String>,
+    upload_destinations: Vec<UploadDestination>,
+    default_destination: Option<String>,
     save_locally: bool,
-) {
+    auto_upload: AutoUpload,
+    last_speakers: std::collections::BTreeMap<String, SpeakerCounts>,
+    loudness_target: Option<f32>,
+    markers: Vec<markers::Marker>,
+) -> Option<String> {
     use std::num::NonZero;
     
     println!("Starting encoding: {} samples at {} Hz -> {} Hz", 
@@ -924,12 +2027,17 @@ fn encode_and_save_recording(
     let resampled = resample_audio(&samples, source_sample_rate, EXPORT_SAMPLE_RATE);
     println!("Resampled to {} samples", resampled.len());
     
+    // Loudness normalization and soft limiting (replaces hard clipping)
+    let (processed, loudness) = dsp::process_for_export(&resampled, EXPORT_SAMPLE_RATE, loudness_target);
+    println!("Loudness: measured {:?} LUFS, output {:?} LUFS, gain {:+.1} dB",
+             loudness.measured_lufs, loudness.output_lufs, loudness.gain_db);
+    
     let file = match File::create(&file_path) {
         Ok(f) => f,
         Err(e) => {
             eprintln!("Failed to create file {}: {}", file_path, e);
             show_notification("Erreur", &format!("Impossible de créer le fichier: {}", e));
-            return;
+            return None;
         }
     };
     
@@ -937,24 +2045,28 @@ fn encode_and_save_recording(
     let channels = NonZero::new(1u8).unwrap();
     
     let encoder_result = VorbisEncoderBuilder::new(sample_rate, channels, file)
-        .and_then(|mut builder| builder.build());
+        .and_then(|mut builder| {
+            builder.comment_tags(loudness_comment_tags(&loudness, loudness_target))?;
+            builder.comment_tags(markers::chapter_tags(&markers))?;
+            builder.build()
+        });
     
     let mut encoder = match encoder_result {
         Ok(enc) => enc,
         Err(e) => {
             eprintln!("Failed to create encoder: {:?}", e);
             show_notification("Erreur", "Impossible de créer l'encodeur audio");
-            return;
+            return None;
         }
     };
     
     // Encode in chunks (1 second at a time)
     let chunk_size = EXPORT_SAMPLE_RATE as usize;
-    let num_samples = resampled.len();
+    let num_samples = processed.len();
     
     for start in (0..num_samples).step_by(chunk_size) {
         let end = (start + chunk_size).min(num_samples);
-        let chunk = vec![resampled[start..end].to_vec()];
+        let chunk = vec![processed[start..end].to_vec()];
         if let Err(e) = encoder.encode_audio_block(chunk) {
             eprintln!("Encoding error: {:?}", e);
         }
@@ -963,72 +2075,159 @@ fn encode_and_save_recording(
     if let Err(e) = encoder.finish() {
         eprintln!("Error finishing encoder: {:?}", e);
         show_notification("Erreur", "Erreur lors de la finalisation de l'encodage");
-        return;
+        return None;
     }
     
     println!("Saved: {}", file_path);
     show_notification("Enregistrement sauvegardé", &file_path);
+    refresh_tray_recordings();
+    
+    if !markers.is_empty() {
+        let duration_seconds = num_samples as f64 / EXPORT_SAMPLE_RATE as f64;
+        if let Err(e) = markers::write_sidecars(&file_path, &markers, duration_seconds) {
+            eprintln!("Failed to write marker files: {}", e);
+        } else {
+            println!("Saved {} markers", markers.len());
+        }
+    }
     
     // Upload to N8N if enabled - send request to main thread for dialog
-    if n8n_enabled {
-        if let Some(endpoint) = n8n_endpoint {
-            // Send upload request to main thread which will show the dialog
-            if let Ok(guard) = UPLOAD_DIALOG_SENDER.lock() {
-                if let Some(sender) = guard.as_ref() {
-                    let request = UploadDialogRequest {
-                        file_path: file_path.clone(),
-                        endpoint,
-                        save_locally,
-                    };
-                    if let Err(e) = sender.send(request) {
-                        eprintln!("Failed to send upload dialog request: {}", e);
-                    }
-                } else {
-                    eprintln!("Upload dialog sender not initialized");
-                }
+    if n8n_enabled && !upload_destinations.is_empty() {
+        // Only the upload dialog shows the estimate
+        let estimated_speakers = if auto_upload.enabled && !auto_upload.countdown_enabled {
+            None
+        } else {
+            speakers::estimate_count(&processed, EXPORT_SAMPLE_RATE)
+        };
+        let request = UploadDialogRequest {
+            file_path: file_path.clone(),
+            destinations: upload_destinations,
+            default_destination,
+            save_locally,
+            last_speakers,
+            estimated_speakers,
+        };
+        match auto_upload {
+            AutoUpload { enabled: false, .. } => send_ui_message(UiMessage::UploadDialog(request)),
+            AutoUpload { countdown_enabled: true, countdown_secs, .. } => {
+                send_ui_message(UiMessage::UploadCountdown(request, countdown_secs))
             }
+            AutoUpload { .. } => request.enqueue_with_defaults(),
         }
     }
+    
+    Some(file_path)
+}
+
+/// Vorbis comment tags describing the loudness stage
+fn loudness_comment_tags(report: &dsp::LoudnessReport, target: Option<f32>) -> Vec<(String, String)> {
+    let mut tags = vec![("ENCODER".to_string(), "audio-recorder".to_string())];
+    if let Some(measured) = report.measured_lufs {
+        tags.push(("LOUDNESS_MEASURED".to_string(), format!("{:.1} LUFS", measured)));
+    }
+    if let Some(output) = report.output_lufs {
+        tags.push(("LOUDNESS_INTEGRATED".to_string(), format!("{:.1} LUFS", output)));
+    }
+    if let Some(target) = target {
+        tags.push(("LOUDNESS_TARGET".to_string(), format!("{:.1} LUFS", target)));
+        tags.push(("LOUDNESS_GAIN".to_string(), format!("{:+.2} dB", report.gain_db)));
+    }
+    tags
+}
+
+/// Send a recording to a destination, whatever its target
+fn upload_recording(file_path: &str, destination: &UploadDestination, min_speakers: u32, max_speakers: u32) -> Result<upload_request::UploadResponse, Box<dyn std::error::Error + Send + Sync>> {
+    // Storage targets only use the metadata to expand their path templates
+    let file_name = std::path::Path::new(file_path)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("recording.ogg");
+    let metadata = upload_request::UploadMetadata {
+        file_name,
+        timestamp: Local::now(),
+        min_speakers,
+        max_speakers,
+        markers_json: None,
+        destination: &destination.name,
+        size: std::fs::metadata(file_path)?.len() as usize,
+    };
+    let location = match &destination.target {
+        UploadTarget::Webhook => return upload_to_n8n_sync(file_path, destination, min_speakers, max_speakers),
+        UploadTarget::S3(s3) => {
+            let key = upload_s3::upload(file_path, &destination.url, s3, &metadata)?;
+            format!("s3://{}/{}", s3.bucket, key)
+        }
+        UploadTarget::Webdav(webdav) => {
+            upload_webdav::upload(file_path, &destination.url, webdav, &destination.auth, &metadata)?
+        }
+    };
+    println!("Uploaded to {}: {}", destination.name, location);
+    Ok(upload_request::UploadResponse {
+        location,
+        ..Default::default()
+    })
 }
 
 /// Synchronous N8N upload (for use in background thread)
-fn upload_to_n8n_sync(file_path: &str, endpoint: &str, min_speakers: u32, max_speakers: u32) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    println!("Starting upload to N8N: {} -> {} (speakers: {}-{})", file_path, endpoint, min_speakers, max_speakers);
+fn upload_to_n8n_sync(file_path: &str, destination: &UploadDestination, min_speakers: u32, max_speakers: u32) -> Result<upload_request::UploadResponse, Box<dyn std::error::Error + Send + Sync>> {
+    println!("Starting upload to {}: {} -> {} (speakers: {}-{})", destination.name, file_path, destination.url, min_speakers, max_speakers);
     
-    let file_content = std::fs::read(file_path)?;
+    let size = std::fs::metadata(file_path)?.len();
     let filename = std::path::Path::new(file_path)
         .file_name()
         .and_then(|n| n.to_str())
         .unwrap_or("recording.ogg")
         .to_string();
     
-    let file_part = reqwest::blocking::multipart::Part::bytes(file_content)
-        .file_name(filename.clone())
-        .mime_str("audio/ogg")?;
-    
-    let form = reqwest::blocking::multipart::Form::new()
-        .part("file", file_part)
-        .text("filename", filename)
-        .text("timestamp", Local::now().to_rfc3339())
-        .text("min_speakers", min_speakers.to_string())
-        .text("max_speakers", max_speakers.to_string());
+    // Markers set during the recording, as the JSON array of the sidecar file
+    let markers_json = markers::read_json(file_path);
+    let metadata = upload_request::UploadMetadata {
+        file_name: &filename,
+        timestamp: Local::now(),
+        min_speakers,
+        max_speakers,
+        markers_json: markers_json.as_deref(),
+        destination: &destination.name,
+        size: size as usize,
+    };
     
-    let client = reqwest::blocking::Client::builder()
-        .timeout(std::time::Duration::from_secs(60))
-        .build()?;
+    // The file is read once to sign the body (HMAC), then again while it is sent
+    let prepared = destination
+        .request
+        .prepare(&destination.url, &destination.fields, &metadata, std::path::Path::new(file_path))?;
+    let client = reqwest::blocking::Client::new();
     
-    let response = client
-        .post(endpoint)
-        .multipart(form)
+    let method = reqwest::Method::from_bytes(prepared.method.as_str().as_bytes())?;
+    let mut request = client
+        .request(method, &prepared.url)
+        .timeout(upload_progress::timeout_for(prepared.body.content_length()));
+    for (name, value) in &prepared.headers {
+        request = request.header(name.as_str(), value.as_str());
+    }
+    let response = upload_auth::authorize(request, &destination.auth, &destination.name, &mut prepared.body.reader()?)?
+        .body(upload_progress::body(prepared.body.reader()?, prepared.body.content_length()))
         .send()?;
     
     if response.status().is_success() {
-        Ok(())
+        let text = response.text()?;
+        Ok(destination.request.response.parse(&prepared.url, &text))
     } else {
         Err(format!("HTTP {}: {}", response.status(), response.text()?).into())
     }
 }
 
+/// Write a file through a temporary sibling renamed over it, so a crash never leaves
+/// it truncated (upload queue and history)
+fn write_file_atomically(path: &std::path::Path, contents: &[u8]) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    std::fs::write(&tmp_path, contents)?;
+    std::fs::rename(&tmp_path, path)
+}
+
 fn show_notification(title: &str, body: &str) {
     if let Ok(guard) = NOTIFIER.lock() {
         if let Some(notifier) = guard.as_ref() {
@@ -1037,6 +2236,15 @@ fn show_notification(title: &str, body: &str) {
     }
 }
 
+/// Notification with an action opening `link`
+fn show_notification_with_link(title: &str, body: &str, link: &str) {
+    if let Ok(guard) = NOTIFIER.lock() {
+        if let Some(notifier) = guard.as_ref() {
+            notifier.notify_with_link(title, body, link);
+        }
+    }
+}
+
 fn build_ui(app: &Application) {
     let window = ApplicationWindow::builder()
         .application(app)
@@ -1083,6 +2291,10 @@ fn build_ui(app: &Application) {
             color: #1f2937;
             font-weight: bold;
         }
+        .status-label {
+            font-size: 10px;
+            color: #666;
+        }
         /* Settings dialog styles */
         .settings-combo {
             min-height: 30px;
@@ -1115,6 +2327,10 @@ fn build_ui(app: &Application) {
             font-size: 12px;
             padding: 4px 8px;
         }
+        .settings-error {
+            font-size: 11px;
+            color: #ef4444;
+        }
         window.dialog headerbar {
             min-height: 38px;
         }
@@ -1129,6 +2345,23 @@ fn build_ui(app: &Application) {
         .waveform-label-system {
             color: #3b82f6;
         }
+        .meter-value {
+            font-size: 9px;
+            font-family: monospace;
+            color: #666;
+        }
+        .clip-led {
+            font-size: 8px;
+            font-weight: bold;
+            padding: 0 4px;
+            border-radius: 3px;
+            background: #e5e7eb;
+            color: #9ca3af;
+        }
+        .clip-led-active {
+            background: #ef4444;
+            color: white;
+        }
     ");
     
     gtk4::style_context_add_provider_for_display(
@@ -1158,24 +2391,12 @@ fn build_ui(app: &Application) {
     let tray_state = Arc::new(Mutex::new(TrayRecordingState::default()));
     *RECORDING_STATE.lock().unwrap() = Some(Arc::clone(&tray_state));
     
-    // Setup channel for upload dialog requests
-    let (sender, receiver) = std::sync::mpsc::channel::<UploadDialogRequest>();
-    *UPLOAD_DIALOG_SENDER.lock().unwrap() = Some(sender);
+    // Expose Start/Stop/Pause/Status on the session bus
+    control::start();
     
-    // Handle upload dialog requests on main thread via timeout polling
-    let window_for_dialog = window.clone();
-    glib::timeout_add_local(Duration::from_millis(100), move || {
-        // Check for pending upload dialog requests (non-blocking)
-        while let Ok(request) = receiver.try_recv() {
-            show_upload_dialog(Some(&window_for_dialog), request);
-        }
-        glib::ControlFlow::Continue
-    });
+    // Global shortcuts through the desktop portal, if available
+    shortcuts::register(&state.borrow().shortcuts.lock().unwrap(), handle_shortcut);
     
-    // Setup window visibility control (needed early for close button)
-    let visible = Arc::new(Mutex::new(false)); // Start hidden
-    *WINDOW_VISIBLE.lock().unwrap() = Some(Arc::clone(&visible));
-
     // Main container
     let vbox = gtk4::Box::new(Orientation::Vertical, 0);
     
@@ -1206,19 +2427,20 @@ fn build_ui(app: &Application) {
     });
     titlebar.append(&settings_button);
     
+    // Spectrum view toggle
+    let spectrum_button = gtk4::ToggleButton::with_label("📊");
+    spectrum_button.add_css_class("close-button");
+    spectrum_button.set_tooltip_text(Some("Spectre / spectrogramme"));
+    titlebar.append(&spectrum_button);
+    
     // Close button (right side)
     let close_button = Button::with_label("×");
     close_button.add_css_class("close-button");
     close_button.set_tooltip_text(Some("Hide window"));
-    let visible_for_close = Arc::clone(&visible);
+    let window_for_close = window.clone();
     close_button.connect_clicked(move |_| {
-        println!("Close button clicked!");
-        if let Ok(mut v) = visible_for_close.try_lock() {
-            println!("Setting visible to false");
-            *v = false;
-        } else {
-            println!("Failed to lock visible");
-        }
+        println!("Hiding window");
+        window_for_close.hide();
     });
     titlebar.append(&close_button);
     
@@ -1251,6 +2473,9 @@ fn build_ui(app: &Application) {
     });
     
     content.append(&mic_drawing_area);
+    
+    let mic_meter = build_meter_row(&state.borrow().input_meter);
+    content.append(&mic_meter.row);
 
     // System audio waveform section
     let system_label = gtk4::Label::new(Some("🔊 System"));
@@ -1273,6 +2498,9 @@ fn build_ui(app: &Application) {
     });
     
     content.append(&system_drawing_area);
+    
+    let system_meter = build_meter_row(&state.borrow().output_meter);
+    content.append(&system_meter.row);
 
     // Controls
     let controls = gtk4::Box::new(Orientation::Horizontal, 20);
@@ -1284,7 +2512,9 @@ fn build_ui(app: &Application) {
     pause_button.connect_clicked(move |_| {
         let mut state = state_clone.borrow_mut();
         if state.recording {
-            state.paused = !state.paused;
+            let paused = !state.paused;
+            state.set_paused(paused);
+            publish_recording_state(true, paused);
         }
     });
     controls.append(&pause_button);
@@ -1299,21 +2529,153 @@ fn build_ui(app: &Application) {
     let state_clone = Rc::clone(&state);
     let mic_drawing_area_clone = mic_drawing_area.clone();
     let system_drawing_area_clone = system_drawing_area.clone();
+    let timer_label_for_record = timer_label.clone();
     record_button.connect_clicked(move |button| {
         let mut state = state_clone.borrow_mut();
         if state.recording {
             state.stop_recording();
             button.set_label("⏺");
+            timer_label_for_record.set_text(&format_duration(0));
         } else {
             state.start_recording();
             button.set_label("⏹");
         }
+        publish_recording_state(state.recording, state.paused);
         mic_drawing_area_clone.queue_draw();
         system_drawing_area_clone.queue_draw();
     });
     controls.append(&record_button);
 
+    // Marker button: the offset is taken on click, the note can be typed afterwards
+    let marker_button = gtk4::MenuButton::builder()
+        .label("🔖")
+        .tooltip_text("Ajouter un marqueur")
+        .sensitive(false)
+        .build();
+    let marker_note_entry = gtk4::Entry::builder()
+        .placeholder_text("Note (optionnelle)")
+        .width_chars(24)
+        .build();
+    marker_note_entry.add_css_class("settings-entry");
+    let marker_popover = gtk4::Popover::builder()
+        .child(&marker_note_entry)
+        .build();
+    marker_button.set_popover(Some(&marker_popover));
+    
+    // Enabled while recording, the count of markers in the tooltip
+    let state_clone = Rc::clone(&state);
+    let marker_button_clone = marker_button.clone();
+    record_button.connect_clicked(move |_| refresh_marker_button(&marker_button_clone, &state_clone.borrow()));
+    
+    let pending_marker: Rc<std::cell::Cell<Option<usize>>> = Rc::new(std::cell::Cell::new(None));
+    let state_clone = Rc::clone(&state);
+    let pending_marker_clone = Rc::clone(&pending_marker);
+    let marker_note_entry_clone = marker_note_entry.clone();
+    let marker_button_clone = marker_button.clone();
+    marker_popover.connect_show(move |_| {
+        let mut state = state_clone.borrow_mut();
+        pending_marker_clone.set(state.add_marker(""));
+        marker_note_entry_clone.set_text("");
+        refresh_marker_button(&marker_button_clone, &state);
+    });
+    let state_clone = Rc::clone(&state);
+    let marker_popover_clone = marker_popover.clone();
+    marker_note_entry.connect_activate(move |entry| {
+        if let Some(index) = pending_marker.take() {
+            if let Some(marker) = state_clone.borrow_mut().markers.get_mut(index) {
+                marker.note = entry.text().trim().to_string();
+            }
+        }
+        marker_popover_clone.popdown();
+    });
+    controls.append(&marker_button);
+
     content.append(&controls);
+    
+    // Estimated file size and remaining recording time
+    let status_label = gtk4::Label::new(None);
+    status_label.add_css_class("status-label");
+    status_label.set_halign(gtk4::Align::Center);
+    content.append(&status_label);
+    
+    // Upload in flight, with a button to cancel it
+    let upload_row = gtk4::Box::new(Orientation::Horizontal, 6);
+    upload_row.set_visible(false);
+    let upload_progress_bar = gtk4::ProgressBar::builder()
+        .show_text(true)
+        .hexpand(true)
+        .valign(gtk4::Align::Center)
+        .build();
+    upload_progress_bar.set_ellipsize(gtk4::pango::EllipsizeMode::Middle);
+    upload_progress_bar.add_css_class("status-label");
+    let cancel_upload_button = Button::with_label("✕");
+    cancel_upload_button.add_css_class("close-button");
+    cancel_upload_button.set_tooltip_text(Some("Annuler l'envoi"));
+    cancel_upload_button.connect_clicked(|_| upload_queue::cancel_upload());
+    upload_row.append(&upload_progress_bar);
+    upload_row.append(&cancel_upload_button);
+    content.append(&upload_row);
+    
+    // Expanded analysis view: live spectrum and spectrogram for each source
+    let mic_spectrum_view = Rc::new(RefCell::new(spectrum::SpectrumView::default()));
+    let system_spectrum_view = Rc::new(RefCell::new(spectrum::SpectrumView::default()));
+    
+    let spectrum_revealer = gtk4::Revealer::new();
+    let spectrum_box = gtk4::Box::new(Orientation::Vertical, 4);
+    spectrum_box.set_margin_top(8);
+    let spectrum_sources = [
+        ("🎤 Mic", "waveform-label-mic", Rc::clone(&mic_spectrum_view), (0.13, 0.77, 0.37)),
+        ("🔊 System", "waveform-label-system", Rc::clone(&system_spectrum_view), (0.23, 0.51, 0.96)),
+    ];
+    let mut spectrum_areas = Vec::new();
+    for (title, css_class, view, color) in spectrum_sources {
+        let label = gtk4::Label::new(Some(title));
+        label.set_halign(gtk4::Align::Start);
+        label.add_css_class("waveform-label");
+        label.add_css_class(css_class);
+        spectrum_box.append(&label);
+        
+        let spectrum_area = DrawingArea::new();
+        spectrum_area.set_content_width(348);
+        spectrum_area.set_content_height(40);
+        let view_for_spectrum = Rc::clone(&view);
+        spectrum_area.set_draw_func(move |_area, cr, width, height| {
+            draw_spectrum(cr, &view_for_spectrum.borrow(), width, height, color);
+        });
+        spectrum_box.append(&spectrum_area);
+        
+        let spectrogram_area = DrawingArea::new();
+        spectrogram_area.set_content_width(348);
+        spectrogram_area.set_content_height(48);
+        spectrogram_area.set_draw_func(move |_area, cr, width, height| {
+            draw_spectrogram(cr, &view.borrow(), width, height);
+        });
+        spectrum_box.append(&spectrogram_area);
+        
+        spectrum_areas.push(spectrum_area);
+        spectrum_areas.push(spectrogram_area);
+    }
+    spectrum_revealer.set_child(Some(&spectrum_box));
+    content.append(&spectrum_revealer);
+    
+    // Capture callbacks only feed the analyser while the view is open
+    let state_for_spectrum = Rc::clone(&state);
+    let revealer_for_toggle = spectrum_revealer.clone();
+    let views_for_toggle = [Rc::clone(&mic_spectrum_view), Rc::clone(&system_spectrum_view)];
+    spectrum_button.connect_toggled(move |button| {
+        let enabled = button.is_active();
+        let state = state_for_spectrum.borrow();
+        for tap in [&state.input_spectrum, &state.output_spectrum] {
+            let mut tap = tap.lock().unwrap();
+            tap.enabled = enabled;
+            tap.clear();
+        }
+        for view in &views_for_toggle {
+            view.borrow_mut().clear();
+        }
+        revealer_for_toggle.set_reveal_child(enabled);
+    });
+    
     vbox.append(&content);
     window.set_child(Some(&vbox));
     
@@ -1325,69 +2687,380 @@ fn build_ui(app: &Application) {
     // Note: We don't auto-hide on focus loss because it interferes with dragging
     // User can hide window by clicking tray icon again or using tray menu
     
-    // Monitor visibility changes from tray icon and close button
+    // Requests from the tray, command line, D-Bus and shortcuts. Recording actions go
+    // through the buttons so every source gets the same behaviour.
+    let state_clone = Rc::clone(&state);
+    let app_clone = app.clone();
     let window_clone = window.clone();
-    let visible_clone = Arc::clone(&visible);
-    glib::timeout_add_local(Duration::from_millis(50), move || {
-        if let Ok(should_be_visible) = visible_clone.try_lock() {
-            let is_visible = window_clone.is_visible();
-            if *should_be_visible && !is_visible {
-                println!("Showing window");
-                window_clone.present();
-            } else if !*should_be_visible && is_visible {
+    let record_button_clone = record_button.clone();
+    let pause_button_clone = pause_button.clone();
+    let marker_button_clone = marker_button.clone();
+    let upload_row_clone = upload_row.clone();
+    // Loopback source restored when system audio is switched back on from the tray
+    let last_loopback_index = std::cell::Cell::new(None);
+    // The tray only follows whole percents
+    let last_tray_upload: RefCell<Option<(String, u8)>> = RefCell::new(None);
+    let handler: UiHandler = Box::new(move |message| match message {
+        // Requests that don't apply to the current state are dropped
+        UiMessage::StartRecording => {
+            if !state_clone.borrow().recording {
+                record_button_clone.emit_clicked();
+            }
+        }
+        UiMessage::StopRecording => {
+            if state_clone.borrow().recording {
+                record_button_clone.emit_clicked();
+            }
+        }
+        UiMessage::TogglePause => pause_button_clone.emit_clicked(),
+        UiMessage::AddMarker => {
+            let mut state = state_clone.borrow_mut();
+            state.add_marker("");
+            refresh_marker_button(&marker_button_clone, &state);
+        }
+        UiMessage::ToggleWindow => {
+            if window_clone.is_visible() {
                 println!("Hiding window");
                 window_clone.hide();
+            } else {
+                println!("Showing window");
+                window_clone.present();
+            }
+        }
+        UiMessage::ShowWindow => window_clone.present(),
+        // Source changes from the tray apply to the next recording
+        UiMessage::SelectMic(_) | UiMessage::SelectLoopback(_) | UiMessage::ToggleSystemAudio
+            if state_clone.borrow().recording => {}
+        UiMessage::SelectMic(idx) => {
+            let mut state = state_clone.borrow_mut();
+            state.selected_mic_index = Some(idx);
+            state.save_config();
+            sync_tray_settings(&state);
+        }
+        UiMessage::SelectLoopback(idx) => {
+            let mut state = state_clone.borrow_mut();
+            state.selected_loopback_index = idx;
+            state.save_config();
+            sync_tray_settings(&state);
+        }
+        UiMessage::ToggleSystemAudio => {
+            let mut state = state_clone.borrow_mut();
+            state.selected_loopback_index = match state.selected_loopback_index {
+                Some(idx) => {
+                    last_loopback_index.set(Some(idx));
+                    None
+                }
+                None => last_loopback_index
+                    .get()
+                    .or_else(|| state.available_sources.iter().position(|s| s.is_monitor)),
+            };
+            state.save_config();
+            sync_tray_settings(&state);
+        }
+        // While quitting the recording is kept locally and not uploaded
+        UiMessage::UploadDialog(request) if QUITTING.load(Ordering::SeqCst) => {
+            println!("Quitting: upload skipped, recording kept at {}", request.file_path);
+        }
+        UiMessage::UploadDialog(request) => show_upload_dialog(Some(&window_clone), request),
+        // Nobody to intervene while quitting: send it now
+        UiMessage::UploadCountdown(request, _) if QUITTING.load(Ordering::SeqCst) => request.enqueue_with_defaults(),
+        UiMessage::UploadCountdown(request, seconds) => show_upload_countdown(&window_clone, request, seconds),
+        UiMessage::RememberSpeakers(destination, speakers) => {
+            let state = state_clone.borrow();
+            let destinations = state.upload_destinations.lock().unwrap().clone();
+            let mut last_speakers = state.last_speakers.lock().unwrap();
+            last_speakers.insert(destination, speakers);
+            // Forget the destinations removed since
+            last_speakers.retain(|name, _| destinations.iter().any(|d| d.name == *name));
+            drop(last_speakers);
+            state.save_config();
+        }
+        UiMessage::UploadQueueChanged => {
+            let jobs = upload_queue::jobs();
+            let pending_uploads = jobs.len();
+            update_tray(move |tray| tray.pending_uploads = pending_uploads);
+            refresh_upload_queue_list(&jobs);
+        }
+        UiMessage::UploadProgressChanged => {
+            let upload = upload_progress::current();
+            upload_row_clone.set_visible(upload.is_some());
+            if let Some(upload) = &upload {
+                upload_progress_bar.set_fraction(upload.fraction());
+                upload_progress_bar.set_text(Some(&format!(
+                    "⬆ {} · {} / {}",
+                    upload.label,
+                    format_size(upload.sent.min(upload.total)),
+                    format_size(upload.total)
+                )));
+            }
+            let tray_upload = upload.as_ref().map(|upload| (upload.label.clone(), upload.percent()));
+            if tray_upload != *last_tray_upload.borrow() {
+                *last_tray_upload.borrow_mut() = tray_upload;
+                update_tray(move |tray| tray.upload = upload);
+            }
+        }
+        UiMessage::ShowUploadQueue => show_upload_queue_dialog(&window_clone),
+        UiMessage::UrlSecretsMigrated => {
+            let mut state = state_clone.borrow_mut();
+            state.secrets_by_destination = true;
+            state.save_config();
+        }
+        UiMessage::Quit { interactive } => {
+            if QUITTING.load(Ordering::SeqCst) {
+                // A second signal exits without waiting for the background threads
+                if !interactive {
+                    eprintln!("Quit requested again, exiting now");
+                    std::process::exit(1);
+                }
+                return;
+            }
+            let recording = state_clone.borrow().recording;
+            let quit_action = *state_clone.borrow().quit_action.lock().unwrap();
+            match quit_action {
+                QuitAction::Ask if recording && interactive => {
+                    show_quit_dialog(&app_clone, &window_clone, &state_clone, &record_button_clone);
+                }
+                QuitAction::Discard => quit_application(&app_clone, &window_clone, &state_clone, &record_button_clone, false),
+                // Without anyone to ask, keep the audio
+                QuitAction::Ask | QuitAction::Save => {
+                    quit_application(&app_clone, &window_clone, &state_clone, &record_button_clone, true)
+                }
             }
         }
-        glib::ControlFlow::Continue
     });
+    UI_HANDLER.with(|ui_handler| *ui_handler.borrow_mut() = Some(handler));
+    sync_tray_settings(&state.borrow());
+    upload_queue::start();
+    
+    // Once, off the main thread: the keyring may prompt for its password. Retried on
+    // the next start if it fails; uploads needing a secret fail and retry meanwhile.
+    if !state.borrow().secrets_by_destination {
+        let destinations = state.borrow().upload_destinations.lock().unwrap().clone();
+        std::thread::spawn(move || match migrate_url_secrets(&destinations) {
+            Ok(()) => send_ui_message(UiMessage::UrlSecretsMigrated),
+            Err(e) => eprintln!("Failed to move upload secrets to their destinations: {}", e),
+        });
+    }
 
     // Update timer and waveform
     let state_clone = Rc::clone(&state);
     let timer_label_clone = timer_label.clone();
     let mic_drawing_area_clone = mic_drawing_area.clone();
     let system_drawing_area_clone = system_drawing_area.clone();
-    let record_button_clone = record_button.clone();
-    let tray_state_clone = Arc::clone(&tray_state);
-    glib::timeout_add_local(Duration::from_millis(50), move || {
+    let window_clone = window.clone();
+    let mic_label_clone = mic_label.clone();
+    let meter_rows = [mic_meter, system_meter];
+    let spectrum_revealer_clone = spectrum_revealer.clone();
+    let status_label_clone = status_label.clone();
+    let free_disk_space: Rc<std::cell::Cell<Option<u64>>> = Rc::new(std::cell::Cell::new(None));
+    let disk_space_pending = Rc::new(std::cell::Cell::new(false));
+    let mut disk_space_checked: Option<Instant> = None;
+    let mut last_tray_refresh = Instant::now();
+    // Redraw loop for the timer, meters and waveforms (state changes are pushed, not polled
+    // here). It stops while the window is hidden and nothing is being recorded.
+    let redraw = Rc::new(RefCell::new(move || {
         let mut state = state_clone.borrow_mut();
+        if !window_clone.is_visible() && !state.recording {
+            return glib::ControlFlow::Break;
+        }
         
-        // Check for tray icon commands
-        if let Ok(mut tray) = tray_state_clone.try_lock() {
-            if tray.start_requested && !state.recording {
-                state.start_recording();
-                record_button_clone.set_label("⏹");
-                tray.start_requested = false;
-            }
-            if tray.stop_requested && state.recording {
-                state.stop_recording();
-                record_button_clone.set_label("⏺");
-                timer_label_clone.set_text("00:00:00");
-                tray.stop_requested = false;
+        if state.recording && !state.paused {
+            state.elapsed = state.recording_offset();
+            timer_label_clone.set_text(&format_duration(state.elapsed.as_secs()));
+            tray_state.lock().unwrap().elapsed_secs = state.elapsed.as_secs();
+        }
+        
+        // Elapsed time and levels in the tray menu and tooltip
+        if state.recording && last_tray_refresh.elapsed() >= TRAY_REFRESH {
+            last_tray_refresh = Instant::now();
+            let elapsed_secs = state.elapsed.as_secs();
+            let mic_level_db = state.input_meter.lock().unwrap().rms_db;
+            let system_level_db = state.output_meter.lock().unwrap().rms_db;
+            update_tray(move |tray| {
+                tray.elapsed_secs = elapsed_secs;
+                tray.mic_level_db = mic_level_db;
+                tray.system_level_db = system_level_db;
+            });
+        }
+        
+        // Refresh free disk space every few seconds, one query at a time
+        if !disk_space_pending.get() && disk_space_checked.is_none_or(|checked| checked.elapsed() >= DISK_SPACE_REFRESH) {
+            let dir = state.save_directory.lock().unwrap().clone().unwrap_or_else(|| ".".to_string());
+            disk_space_checked = Some(Instant::now());
+            disk_space_pending.set(true);
+            let free_disk_space = Rc::clone(&free_disk_space);
+            let disk_space_pending = Rc::clone(&disk_space_pending);
+            query_disk_space(&dir, move |free| {
+                free_disk_space.set(free);
+                disk_space_pending.set(false);
+            });
+        }
+        let free_bytes = free_disk_space.get();
+        
+        let size_text = if state.recording {
+            format!("≈ {}", format_size(estimated_export_size(state.elapsed.as_secs())))
+        } else {
+            format!("≈ {}/h", format_size(estimated_export_size(3600)))
+        };
+        let status_text = match free_bytes {
+            Some(free) => {
+                let remaining_secs = free * 8 / ESTIMATED_EXPORT_BITRATE;
+                format!("{} · {} restantes sur le disque", size_text, format_duration(remaining_secs))
             }
-            if tray.pause_requested && state.recording {
-                state.paused = !state.paused;
-                tray.pause_requested = false;
+            None => size_text,
+        };
+        if status_label_clone.text() != status_text {
+            status_label_clone.set_text(&status_text);
+        }
+        
+        // Show the effective mic gain while the AGC is adapting
+        let mic_text = {
+            let agc = state.agc.lock().unwrap();
+            if agc.enabled && state.recording {
+                let gain_db = 20.0 * agc.effective_gain().max(1e-9).log10();
+                format!("🎤 Mic · AGC {:+.1} dB", gain_db)
+            } else {
+                "🎤 Mic".to_string()
             }
-            
-            // Update tray state
-            tray.recording = state.recording;
-            tray.paused = state.paused;
+        };
+        if mic_label_clone.text() != mic_text {
+            mic_label_clone.set_text(&mic_text);
         }
         
-        if state.recording && !state.paused {
-            if let Some(start) = state.start_time {
-                state.elapsed = start.elapsed();
-                let secs = state.elapsed.as_secs();
-                let mins = secs / 60;
-                let secs = secs % 60;
-                timer_label_clone.set_text(&format!("00:{:02}:{:02}", mins, secs));
+        for meter_row in &meter_rows {
+            meter_row.refresh();
+        }
+        
+        // Spectrum analysis runs here, on the UI thread, from the latest captured samples
+        if spectrum_revealer_clone.reveals_child() && state.recording && !state.paused {
+            for (tap, view) in [
+                (&state.input_spectrum, &mic_spectrum_view),
+                (&state.output_spectrum, &system_spectrum_view),
+            ] {
+                let (samples, sample_rate) = {
+                    let tap = tap.lock().unwrap();
+                    (tap.snapshot(), tap.sample_rate)
+                };
+                view.borrow_mut().update(&samples, sample_rate);
+            }
+            for area in &spectrum_areas {
+                area.queue_draw();
             }
         }
+        
         mic_drawing_area_clone.queue_draw();
         system_drawing_area_clone.queue_draw();
         glib::ControlFlow::Continue
+    }));
+    let redraw_running = Rc::new(std::cell::Cell::new(false));
+    let start_redraw = move || {
+        if redraw_running.replace(true) {
+            return;
+        }
+        let redraw = Rc::clone(&redraw);
+        let redraw_running = Rc::clone(&redraw_running);
+        glib::timeout_add_local(Duration::from_millis(50), move || {
+            let flow = (redraw.borrow_mut())();
+            if flow == glib::ControlFlow::Break {
+                redraw_running.set(false);
+            }
+            flow
+        });
+    };
+    let start_redraw_clone = start_redraw.clone();
+    window.connect_visible_notify(move |window| {
+        if window.is_visible() {
+            start_redraw_clone();
+        }
+    });
+    let start_redraw_clone = start_redraw.clone();
+    record_button.connect_clicked(move |_| start_redraw_clone());
+    start_redraw();
+}
+
+fn refresh_marker_button(button: &gtk4::MenuButton, state: &RecorderState) {
+    button.set_sensitive(state.recording);
+    button.set_tooltip_text(Some(&match state.markers.len() {
+        0 => "Ajouter un marqueur".to_string(),
+        n => format!("Ajouter un marqueur ({} placé(s))", n),
+    }));
+}
+
+/// Widgets of one dBFS meter line (bar, numeric readout and clip LED)
+struct MeterRow {
+    row: gtk4::Box,
+    area: DrawingArea,
+    value_label: gtk4::Label,
+    clip_led: gtk4::Label,
+    meter: Arc<Mutex<meter::LevelMeter>>,
+}
+
+impl MeterRow {
+    fn refresh(&self) {
+        let (rms_db, peak_db, clipped) = {
+            let meter = self.meter.lock().unwrap();
+            (meter.rms_db, meter.peak_hold_db, meter.clipped)
+        };
+        
+        let text = if peak_db <= meter::METER_FLOOR_DB {
+            "  -∞ dBFS".to_string()
+        } else {
+            format!("{:5.1} / {:5.1} dBFS", rms_db, peak_db)
+        };
+        if self.value_label.text() != text {
+            self.value_label.set_text(&text);
+        }
+        
+        if clipped {
+            self.clip_led.add_css_class("clip-led-active");
+        } else {
+            self.clip_led.remove_css_class("clip-led-active");
+        }
+        self.area.queue_draw();
+    }
+}
+
+fn build_meter_row(meter: &Arc<Mutex<meter::LevelMeter>>) -> MeterRow {
+    let row = gtk4::Box::new(Orientation::Horizontal, 6);
+    
+    let area = DrawingArea::new();
+    area.set_content_height(6);
+    area.set_hexpand(true);
+    area.set_valign(gtk4::Align::Center);
+    let meter_for_draw = Arc::clone(meter);
+    area.set_draw_func(move |_area, cr, width, height| {
+        let meter = meter_for_draw.lock().unwrap();
+        draw_level_meter(cr, &meter, width, height);
     });
+    area.set_tooltip_text(Some("RMS (barre), crête (trait fin) et maintien de crête, en dBFS"));
+    
+    let value_label = gtk4::Label::new(Some("  -∞ dBFS"));
+    value_label.add_css_class("meter-value");
+    value_label.set_width_chars(20);
+    value_label.set_xalign(1.0);
+    
+    // Clip LED latches on overs; click to clear it
+    let clip_led = gtk4::Label::new(Some("CLIP"));
+    clip_led.add_css_class("clip-led");
+    clip_led.set_tooltip_text(Some("Saturation détectée (cliquer pour réinitialiser)"));
+    let click = gtk4::GestureClick::new();
+    let meter_for_click = Arc::clone(meter);
+    click.connect_pressed(move |_, _, _, _| {
+        meter_for_click.lock().unwrap().reset_clip();
+    });
+    clip_led.add_controller(click);
+    
+    row.append(&area);
+    row.append(&value_label);
+    row.append(&clip_led);
+    
+    MeterRow {
+        row,
+        area,
+        value_label,
+        clip_led,
+        meter: Arc::clone(meter),
+    }
 }
 
 fn show_settings_dialog(parent: &ApplicationWindow, state: &Rc<RefCell<RecorderState>>) {
@@ -1452,7 +3125,7 @@ fn show_settings_dialog(parent: &ApplicationWindow, state: &Rc<RefCell<RecorderS
     for (idx, source) in state_borrow.available_sources.iter().enumerate() {
         if !source.is_monitor {
             mic_combo.append(Some(&idx.to_string()), &source.display_name);
-            if idx == state_borrow.selected_mic_index {
+            if Some(idx) == state_borrow.selected_mic_index {
                 selected_mic_idx = combo_idx;
             }
             combo_idx += 1;
@@ -1534,6 +3207,161 @@ fn show_settings_dialog(parent: &ApplicationWindow, state: &Rc<RefCell<RecorderS
     gain_box.append(&gain_value_label);
     vbox.append(&gain_box);
     
+    // Automatic gain control section
+    let agc_box = GtkBox::new(Orientation::Horizontal, 6);
+    let agc_check = gtk4::CheckButton::new();
+    let agc_label = Label::builder()
+        .label("Gain automatique (AGC), cible :")
+        .halign(gtk4::Align::Start)
+        .hexpand(true)
+        .build();
+    agc_label.add_css_class("settings-label");
+    
+    let agc_target_spin = gtk4::SpinButton::with_range(-40.0, -6.0, 1.0);
+    agc_target_spin.set_digits(0);
+    agc_target_spin.add_css_class("settings-entry");
+    let agc_target_unit = Label::new(Some("dBFS"));
+    agc_target_unit.add_css_class("settings-label");
+    
+    agc_box.append(&agc_check);
+    agc_box.append(&agc_label);
+    agc_box.append(&agc_target_spin);
+    agc_box.append(&agc_target_unit);
+    vbox.append(&agc_box);
+    
+    let agc_timing_box = GtkBox::new(Orientation::Horizontal, 6);
+    let agc_attack_label = Label::new(Some("Attaque (ms)"));
+    agc_attack_label.add_css_class("settings-label");
+    let agc_attack_spin = gtk4::SpinButton::with_range(5.0, 2000.0, 5.0);
+    agc_attack_spin.set_digits(0);
+    agc_attack_spin.add_css_class("settings-entry");
+    let agc_release_label = Label::builder()
+        .label("Relâchement (ms)")
+        .margin_start(12)
+        .build();
+    agc_release_label.add_css_class("settings-label");
+    let agc_release_spin = gtk4::SpinButton::with_range(50.0, 10000.0, 50.0);
+    agc_release_spin.set_digits(0);
+    agc_release_spin.add_css_class("settings-entry");
+    
+    agc_timing_box.append(&agc_attack_label);
+    agc_timing_box.append(&agc_attack_spin);
+    agc_timing_box.append(&agc_release_label);
+    agc_timing_box.append(&agc_release_spin);
+    vbox.append(&agc_timing_box);
+    
+    {
+        let agc = state_borrow.agc.lock().unwrap();
+        agc_check.set_active(agc.enabled);
+        agc_target_spin.set_value(agc.target_dbfs as f64);
+        agc_attack_spin.set_value(agc.attack_ms as f64);
+        agc_release_spin.set_value(agc.release_ms as f64);
+    }
+    
+    // The fixed gain slider only applies when AGC is off
+    let set_agc_sensitivity = {
+        let gain_scale = gain_scale.clone();
+        let agc_target_spin = agc_target_spin.clone();
+        let agc_timing_box = agc_timing_box.clone();
+        move |enabled: bool| {
+            gain_scale.set_sensitive(!enabled);
+            agc_target_spin.set_sensitive(enabled);
+            agc_timing_box.set_sensitive(enabled);
+        }
+    };
+    set_agc_sensitivity(agc_check.is_active());
+    agc_check.connect_toggled(move |check| {
+        set_agc_sensitivity(check.is_active());
+    });
+    
+    // Mic processing chain section
+    let mic_chain_label = Label::builder()
+        .label("<small>Traitement du micro</small>")
+        .use_markup(true)
+        .halign(gtk4::Align::Start)
+        .margin_top(6)
+        .build();
+    mic_chain_label.add_css_class("settings-label");
+    vbox.append(&mic_chain_label);
+    
+    let mic_chain = *state_borrow.mic_chain.lock().unwrap();
+    let (highpass_row, highpass_check, highpass_spin) = build_toggle_spin_row(
+        "Filtre passe-haut",
+        mic_chain.highpass_enabled,
+        (20.0, 300.0, 10.0),
+        mic_chain.highpass_cutoff_hz as f64,
+        "Hz",
+    );
+    vbox.append(&highpass_row);
+    let (suppression_row, suppression_check, suppression_spin) = build_toggle_spin_row(
+        "Réduction de bruit",
+        mic_chain.suppression_enabled,
+        (3.0, 30.0, 1.0),
+        mic_chain.suppression_db as f64,
+        "dB",
+    );
+    vbox.append(&suppression_row);
+    let (gate_row, gate_check, gate_spin) = build_toggle_spin_row(
+        "Noise gate, seuil :",
+        mic_chain.gate_enabled,
+        (-80.0, -20.0, 1.0),
+        mic_chain.gate_threshold_dbfs as f64,
+        "dBFS",
+    );
+    vbox.append(&gate_row);
+    
+    let aec_box = GtkBox::new(Orientation::Horizontal, 6);
+    let aec_check = gtk4::CheckButton::new();
+    aec_check.set_active(*state_borrow.echo_cancellation.lock().unwrap());
+    let aec_label = Label::builder()
+        .label("Annulation d'écho (haut-parleurs)")
+        .halign(gtk4::Align::Start)
+        .build();
+    aec_label.add_css_class("settings-label");
+    aec_box.append(&aec_check);
+    aec_box.append(&aec_label);
+    aec_box.set_tooltip_text(Some("Utilise l'audio système comme référence pour retirer l'écho capté par le micro"));
+    vbox.append(&aec_box);
+    
+    // Loudness normalization section
+    let loudness_label = Label::builder()
+        .label("<small>Normalisation du volume</small>")
+        .use_markup(true)
+        .halign(gtk4::Align::Start)
+        .margin_top(6)
+        .build();
+    loudness_label.add_css_class("settings-label");
+    vbox.append(&loudness_label);
+    
+    let loudness_box = GtkBox::new(Orientation::Horizontal, 6);
+    let loudness_check = gtk4::CheckButton::new();
+    loudness_check.set_active(*state_borrow.loudness_normalization.lock().unwrap());
+    let loudness_check_label = Label::builder()
+        .label("Normaliser (EBU R128), cible :")
+        .halign(gtk4::Align::Start)
+        .hexpand(true)
+        .build();
+    loudness_check_label.add_css_class("settings-label");
+    
+    let loudness_spin = gtk4::SpinButton::with_range(-30.0, -10.0, 1.0);
+    loudness_spin.set_digits(0);
+    loudness_spin.set_value(*state_borrow.loudness_target_lufs.lock().unwrap() as f64);
+    loudness_spin.add_css_class("settings-entry");
+    loudness_spin.set_sensitive(loudness_check.is_active());
+    let loudness_unit_label = Label::new(Some("LUFS"));
+    loudness_unit_label.add_css_class("settings-label");
+    
+    let loudness_spin_clone = loudness_spin.clone();
+    loudness_check.connect_toggled(move |check| {
+        loudness_spin_clone.set_sensitive(check.is_active());
+    });
+    
+    loudness_box.append(&loudness_check);
+    loudness_box.append(&loudness_check_label);
+    loudness_box.append(&loudness_spin);
+    loudness_box.append(&loudness_unit_label);
+    vbox.append(&loudness_box);
+    
     // Save directory section
     let save_dir_label = Label::builder()
         .label("<small>Dossier d'enregistrement</small>")
@@ -1581,140 +3409,1250 @@ fn show_settings_dialog(parent: &ApplicationWindow, state: &Rc<RefCell<RecorderS
                     }
                 }
             }
-        });
+        });
+        
+        file_chooser.show();
+    });
+    
+    save_dir_box.append(&save_dir_entry);
+    save_dir_box.append(&browse_button);
+    vbox.append(&save_dir_box);
+    
+    // N8N Upload section
+    let n8n_label = Label::builder()
+        .label("<small>Upload N8N</small>")
+        .use_markup(true)
+        .halign(gtk4::Align::Start)
+        .margin_top(10)
+        .build();
+    n8n_label.add_css_class("settings-label");
+    vbox.append(&n8n_label);
+    
+    let n8n_enabled_box = GtkBox::new(Orientation::Horizontal, 6);
+    let n8n_enabled_check = gtk4::CheckButton::new();
+    n8n_enabled_check.set_active(*state_borrow.n8n_enabled.lock().unwrap());
+    let n8n_enabled_label = Label::builder()
+        .label("Activer l'upload vers N8N")
+        .halign(gtk4::Align::Start)
+        .build();
+    n8n_enabled_label.add_css_class("settings-label");
+    n8n_enabled_box.append(&n8n_enabled_check);
+    n8n_enabled_box.append(&n8n_enabled_label);
+    vbox.append(&n8n_enabled_box);
+    
+    let destinations_button = Button::with_label(&format!(
+        "Destinations ({})…",
+        state_borrow.upload_destinations.lock().unwrap().len()
+    ));
+    destinations_button.add_css_class("settings-button");
+    destinations_button.set_halign(gtk4::Align::Start);
+    destinations_button.set_sensitive(*state_borrow.n8n_enabled.lock().unwrap());
+    let destinations_dialog = dialog.clone();
+    let destinations_state = Rc::clone(state);
+    destinations_button.connect_clicked(move |button| {
+        show_destinations_dialog(&destinations_dialog, &destinations_state, button);
+    });
+    
+    let destinations_button_clone = destinations_button.clone();
+    n8n_enabled_check.connect_toggled(move |check| {
+        destinations_button_clone.set_sensitive(check.is_active());
+    });
+    
+    vbox.append(&destinations_button);
+    
+    let n8n_save_locally_box = GtkBox::new(Orientation::Horizontal, 6);
+    let n8n_save_locally_check = gtk4::CheckButton::new();
+    n8n_save_locally_check.set_active(*state_borrow.save_locally.lock().unwrap());
+    let n8n_save_locally_label = Label::builder()
+        .label("Conserver le fichier localement après upload")
+        .halign(gtk4::Align::Start)
+        .build();
+    n8n_save_locally_label.add_css_class("settings-label");
+    n8n_save_locally_box.append(&n8n_save_locally_check);
+    n8n_save_locally_box.append(&n8n_save_locally_label);
+    n8n_save_locally_box.set_margin_top(4);
+    vbox.append(&n8n_save_locally_box);
+    
+    let auto_upload = *state_borrow.auto_upload.lock().unwrap();
+    let auto_upload_box = GtkBox::new(Orientation::Horizontal, 6);
+    let auto_upload_check = gtk4::CheckButton::new();
+    auto_upload_check.set_active(auto_upload.enabled);
+    let auto_upload_label = Label::builder()
+        .label("Envoyer automatiquement vers la destination par défaut")
+        .halign(gtk4::Align::Start)
+        .build();
+    auto_upload_label.add_css_class("settings-label");
+    auto_upload_box.append(&auto_upload_check);
+    auto_upload_box.append(&auto_upload_label);
+    auto_upload_box.set_margin_top(4);
+    auto_upload_box.set_tooltip_text(Some("Sans le dialogue d'envoi, avec les locuteurs par défaut de la destination"));
+    vbox.append(&auto_upload_box);
+    let (countdown_row, countdown_check, countdown_spin) = build_toggle_spin_row(
+        "Compte à rebours pour modifier ou annuler",
+        auto_upload.countdown_enabled,
+        (3.0, 120.0, 1.0),
+        auto_upload.countdown_secs as f64,
+        "s",
+    );
+    countdown_row.set_sensitive(auto_upload.enabled);
+    vbox.append(&countdown_row);
+    let countdown_row_clone = countdown_row.clone();
+    auto_upload_check.connect_toggled(move |check| {
+        countdown_row_clone.set_sensitive(check.is_active());
+    });
+    
+    let upload_queue_button = Button::with_label(&format!("Envois en attente ({})", upload_queue::jobs().len()));
+    upload_queue_button.add_css_class("settings-button");
+    upload_queue_button.set_halign(gtk4::Align::Start);
+    upload_queue_button.set_margin_top(4);
+    let queue_parent = parent.clone();
+    upload_queue_button.connect_clicked(move |_| show_upload_queue_dialog(&queue_parent));
+    vbox.append(&upload_queue_button);
+    
+    // Running recording on quit
+    let quit_label = Label::builder()
+        .label("<small>Enregistrement en cours à la fermeture</small>")
+        .use_markup(true)
+        .halign(gtk4::Align::Start)
+        .margin_top(10)
+        .build();
+    quit_label.add_css_class("settings-label");
+    vbox.append(&quit_label);
+    
+    let quit_combo = ComboBoxText::new();
+    quit_combo.add_css_class("settings-combo");
+    quit_combo.append(Some("ask"), "Demander");
+    quit_combo.append(Some("save"), "Sauvegarder");
+    quit_combo.append(Some("discard"), "Abandonner");
+    quit_combo.set_active_id(Some(match *state_borrow.quit_action.lock().unwrap() {
+        QuitAction::Ask => "ask",
+        QuitAction::Save => "save",
+        QuitAction::Discard => "discard",
+    }));
+    vbox.append(&quit_combo);
+    
+    // Global shortcuts section
+    let shortcuts_label = Label::builder()
+        .label("<small>Raccourcis globaux</small>")
+        .use_markup(true)
+        .halign(gtk4::Align::Start)
+        .margin_top(10)
+        .build();
+    shortcuts_label.add_css_class("settings-label");
+    shortcuts_label.set_tooltip_text(Some(
+        "Format CTRL+ALT+R, vide pour désactiver. Le bureau peut demander confirmation. \
+         Sans portail, associez « audio-recorder --toggle » (ou --pause, --marker) à un raccourci du bureau.",
+    ));
+    vbox.append(&shortcuts_label);
+    
+    let shortcuts = state_borrow.shortcuts.lock().unwrap().clone();
+    let shortcuts_grid = gtk4::Grid::builder()
+        .row_spacing(4)
+        .column_spacing(6)
+        .build();
+    let shortcut_entries: Vec<gtk4::Entry> = [
+        ("Démarrer / arrêter", &shortcuts.toggle_recording),
+        ("Pause / reprise", &shortcuts.pause),
+        ("Ajouter un marqueur", &shortcuts.marker),
+    ]
+    .iter()
+    .enumerate()
+    .map(|(row, (label_text, trigger))| {
+        let label = Label::builder()
+            .label(*label_text)
+            .halign(gtk4::Align::Start)
+            .build();
+        label.add_css_class("settings-label");
+        let entry = gtk4::Entry::new();
+        entry.add_css_class("settings-entry");
+        entry.set_hexpand(true);
+        entry.set_placeholder_text(Some("Aucun"));
+        entry.set_text(trigger);
+        shortcuts_grid.attach(&label, 0, row as i32, 1, 1);
+        shortcuts_grid.attach(&entry, 1, row as i32, 1, 1);
+        entry
+    })
+    .collect();
+    vbox.append(&shortcuts_grid);
+    
+    drop(state_borrow); // Release borrow before showing dialog
+    
+    content_area.append(&vbox);
+    content_area.append(&button_box);
+    
+    let state_clone = Rc::clone(state);
+    dialog.connect_response(move |dialog, response| {
+        if response == ResponseType::Apply || response == ResponseType::Cancel {
+            if response == ResponseType::Apply {
+                let mut state = state_clone.borrow_mut();
+                
+                // Update microphone
+                if let Some(id) = mic_combo.active_id() {
+                    if let Ok(idx) = id.parse::<usize>() {
+                        state.selected_mic_index = Some(idx);
+                        println!("Microphone updated to index: {}", idx);
+                    }
+                }
+                
+                // Update loopback
+                if let Some(id) = loopback_combo.active_id() {
+                    if id == "none" {
+                        state.selected_loopback_index = None;
+                        println!("Loopback disabled");
+                    } else if let Ok(idx) = id.parse::<usize>() {
+                        state.selected_loopback_index = Some(idx);
+                        println!("Loopback updated to index: {}", idx);
+                    }
+                }
+                
+                // Update save directory
+                let dir_text = save_dir_entry.text().to_string();
+                if dir_text.is_empty() || dir_text == "Dossier courant" {
+                    *state.save_directory.lock().unwrap() = None;
+                } else {
+                    // Expand ~ to home directory
+                    let expanded_path = if dir_text.starts_with("~/") {
+                        if let Some(home) = dirs::home_dir() {
+                            home.join(&dir_text[2..]).to_string_lossy().to_string()
+                        } else {
+                            dir_text
+                        }
+                    } else {
+                        dir_text
+                    };
+                    *state.save_directory.lock().unwrap() = Some(expanded_path);
+                    println!("Save directory updated");
+                }
+                
+                // Update N8N settings
+                *state.n8n_enabled.lock().unwrap() = n8n_enabled_check.is_active();
+                
+                *state.save_locally.lock().unwrap() = n8n_save_locally_check.is_active();
+                *state.auto_upload.lock().unwrap() = AutoUpload {
+                    enabled: auto_upload_check.is_active(),
+                    countdown_enabled: countdown_check.is_active(),
+                    countdown_secs: countdown_spin.value() as u32,
+                };
+                
+                // Update automatic gain control
+                {
+                    let mut agc = state.agc.lock().unwrap();
+                    agc.enabled = agc_check.is_active();
+                    agc.target_dbfs = agc_target_spin.value() as f32;
+                    agc.attack_ms = agc_attack_spin.value() as f32;
+                    agc.release_ms = agc_release_spin.value() as f32;
+                }
+                
+                // Update mic processing chain
+                *state.mic_chain.lock().unwrap() = denoise::MicChainSettings {
+                    highpass_enabled: highpass_check.is_active(),
+                    highpass_cutoff_hz: highpass_spin.value() as f32,
+                    suppression_enabled: suppression_check.is_active(),
+                    suppression_db: suppression_spin.value() as f32,
+                    gate_enabled: gate_check.is_active(),
+                    gate_threshold_dbfs: gate_spin.value() as f32,
+                };
+                
+                *state.echo_cancellation.lock().unwrap() = aec_check.is_active();
+                
+                // Update loudness normalization
+                *state.loudness_normalization.lock().unwrap() = loudness_check.is_active();
+                *state.loudness_target_lufs.lock().unwrap() = loudness_spin.value() as f32;
+                
+                // Update global shortcuts, re-registering with the portal if they changed
+                let bindings = shortcuts::ShortcutBindings {
+                    toggle_recording: shortcut_entries[0].text().trim().to_string(),
+                    pause: shortcut_entries[1].text().trim().to_string(),
+                    marker: shortcut_entries[2].text().trim().to_string(),
+                };
+                if *state.shortcuts.lock().unwrap() != bindings {
+                    shortcuts::register(&bindings, handle_shortcut);
+                    *state.shortcuts.lock().unwrap() = bindings;
+                }
+                
+                *state.quit_action.lock().unwrap() = match quit_combo.active_id().as_deref() {
+                    Some("save") => QuitAction::Save,
+                    Some("discard") => QuitAction::Discard,
+                    _ => QuitAction::Ask,
+                };
+                
+                // Save config
+                state.save_config();
+                sync_tray_settings(&state);
+            }
+            dialog.close();
+        }
+    });
+    
+    dialog.present();
+}
+
+/// Upload destinations being edited, with the secret typed for each one and the name
+/// its stored secret is keyed by (None for destinations added in the dialog).
+/// Nothing is saved until the dialog is applied.
+struct DestinationsEditor {
+    entries: RefCell<Vec<(UploadDestination, String, Option<String>)>>,
+    default_name: RefCell<Option<String>>,
+    current: std::cell::Cell<Option<usize>>,
+    // Set while the picker is refilled, so its changed signal is ignored
+    rebuilding: std::cell::Cell<bool>,
+    picker: gtk4::ComboBoxText,
+    form: gtk4::Box,
+    name_entry: gtk4::Entry,
+    target_combo: gtk4::ComboBoxText,
+    url_entry: gtk4::Entry,
+    auth_combo: gtk4::ComboBoxText,
+    auth_name_entry: gtk4::Entry,
+    access_key_entry: gtk4::Entry,
+    secret_entry: gtk4::PasswordEntry,
+    // Widgets of webhook destinations only
+    webhook_box: gtk4::Box,
+    fields_view: gtk4::TextView,
+    method_combo: gtk4::ComboBoxText,
+    body_combo: gtk4::ComboBoxText,
+    // File, filename, timestamp, min/max speakers, markers and metadata JSON field names
+    field_name_entries: Vec<gtk4::Entry>,
+    headers_view: gtk4::TextView,
+    response_url_entry: gtk4::Entry,
+    response_id_entry: gtk4::Entry,
+    // Widgets of WebDAV destinations only
+    webdav_box: gtk4::Box,
+    folder_entry: gtk4::Entry,
+    // Widgets of S3 destinations only
+    s3_box: gtk4::Box,
+    bucket_entry: gtk4::Entry,
+    region_entry: gtk4::Entry,
+    key_template_entry: gtk4::Entry,
+    path_style_check: gtk4::CheckButton,
+    min_speakers_spin: gtk4::SpinButton,
+    max_speakers_spin: gtk4::SpinButton,
+    default_check: gtk4::CheckButton,
+}
+
+/// `name<separator>value` lines of a text view
+fn text_view_pairs(view: &gtk4::TextView, separator: char) -> std::collections::BTreeMap<String, String> {
+    let buffer = view.buffer();
+    buffer
+        .text(&buffer.start_iter(), &buffer.end_iter(), false)
+        .lines()
+        .filter_map(|line| line.split_once(separator))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .filter(|(name, _)| !name.is_empty())
+        .collect()
+}
+
+fn set_text_view_pairs(view: &gtk4::TextView, pairs: &std::collections::BTreeMap<String, String>, separator: &str) {
+    let text: String = pairs
+        .iter()
+        .map(|(name, value)| format!("{}{}{}\n", name, separator, value))
+        .collect();
+    view.buffer().set_text(&text);
+}
+
+impl DestinationsEditor {
+    /// Copy the form into the selected entry
+    fn store_current(&self) {
+        let Some(index) = self.current.get() else {
+            return;
+        };
+        let field_names: Vec<String> = self
+            .field_name_entries
+            .iter()
+            .map(|entry| entry.text().trim().to_string())
+            .collect();
+        let request = upload_request::RequestShape {
+            method: upload_request::HttpMethod::ALL
+                .get(self.method_combo.active().unwrap_or(0) as usize)
+                .copied()
+                .unwrap_or_default(),
+            body: upload_request::BodyMode::ALL
+                .into_iter()
+                .find(|mode| self.body_combo.active_id().as_deref() == Some(mode.id()))
+                .unwrap_or_default(),
+            file_field: field_names[0].clone(),
+            filename_field: field_names[1].clone(),
+            timestamp_field: field_names[2].clone(),
+            min_speakers_field: field_names[3].clone(),
+            max_speakers_field: field_names[4].clone(),
+            markers_field: field_names[5].clone(),
+            metadata_json_field: field_names[6].clone(),
+            headers: text_view_pairs(&self.headers_view, ':'),
+            response: upload_request::ResponseMapping {
+                url: self.response_url_entry.text().trim().to_string(),
+                id: self.response_id_entry.text().trim().to_string(),
+            },
+        };
+        let target = match self.target_combo.active_id().as_deref() {
+            Some("s3") => UploadTarget::S3(upload_s3::S3Target {
+                bucket: self.bucket_entry.text().trim().to_string(),
+                region: self.region_entry.text().trim().to_string(),
+                key_template: self.key_template_entry.text().trim().to_string(),
+                path_style: self.path_style_check.is_active(),
+                access_key_id: self.access_key_entry.text().trim().to_string(),
+            }),
+            Some("webdav") => UploadTarget::Webdav(upload_webdav::WebdavTarget {
+                folder_template: self.folder_entry.text().trim().to_string(),
+            }),
+            _ => UploadTarget::Webhook,
+        };
+        let auth = match target {
+            UploadTarget::S3(_) => upload_auth::AuthMethod::None,
+            UploadTarget::Webhook | UploadTarget::Webdav(_) => upload_auth::AuthMethod::from_kind(
+                &self.auth_combo.active_id().unwrap_or_default(),
+                &self.auth_name_entry.text(),
+            ),
+        };
+        let destination = UploadDestination {
+            name: self.name_entry.text().trim().to_string(),
+            url: self.url_entry.text().trim().to_string(),
+            target,
+            auth,
+            fields: text_view_pairs(&self.fields_view, '='),
+            request,
+            default_speakers: SpeakerCounts {
+                min: self.min_speakers_spin.value() as u32,
+                max: (self.max_speakers_spin.value() as u32).max(self.min_speakers_spin.value() as u32),
+            },
+        };
+        
+        let mut entries = self.entries.borrow_mut();
+        let mut default_name = self.default_name.borrow_mut();
+        if self.default_check.is_active() {
+            *default_name = Some(destination.name.clone());
+        } else if default_name.as_deref() == Some(entries[index].0.name.as_str()) {
+            *default_name = None;
+        }
+        entries[index].0 = destination;
+        entries[index].1 = self.secret_entry.text().to_string();
+    }
+    
+    /// Refill the picker and show the entry at `index` in the form
+    fn show(&self, index: Option<usize>) {
+        self.rebuilding.set(true);
+        self.picker.remove_all();
+        for (idx, (destination, _, _)) in self.entries.borrow().iter().enumerate() {
+            let label = if destination.name.is_empty() { "(sans nom)" } else { destination.name.as_str() };
+            self.picker.append(Some(&idx.to_string()), label);
+        }
+        self.picker.set_active(index.map(|idx| idx as u32));
+        self.rebuilding.set(false);
+        
+        self.current.set(index);
+        self.form.set_sensitive(index.is_some());
+        let (destination, secret, _) = index
+            .and_then(|idx| self.entries.borrow().get(idx).cloned())
+            .unwrap_or_default();
+        self.name_entry.set_text(&destination.name);
+        self.target_combo.set_active_id(Some(destination.target.id()));
+        self.url_entry.set_text(&destination.url);
+        let s3 = match &destination.target {
+            UploadTarget::S3(s3) => s3.clone(),
+            _ => Default::default(),
+        };
+        self.bucket_entry.set_text(&s3.bucket);
+        self.region_entry.set_text(&s3.region);
+        self.key_template_entry.set_text(&s3.key_template);
+        self.path_style_check.set_active(s3.path_style);
+        self.access_key_entry.set_text(&s3.access_key_id);
+        let webdav = match &destination.target {
+            UploadTarget::Webdav(webdav) => webdav.clone(),
+            _ => Default::default(),
+        };
+        self.folder_entry.set_text(&webdav.folder_template);
+        self.min_speakers_spin.set_value(destination.default_speakers.min as f64);
+        self.max_speakers_spin.set_value(destination.default_speakers.max as f64);
+        let (kind, parameter) = destination.auth.kind();
+        self.auth_combo.set_active_id(Some(kind));
+        self.auth_name_entry.set_text(parameter);
+        self.secret_entry.set_text(&secret);
+        set_text_view_pairs(&self.fields_view, &destination.fields, "=");
+        let request = &destination.request;
+        self.method_combo.set_active_id(Some(request.method.as_str()));
+        self.body_combo.set_active_id(Some(request.body.id()));
+        let field_names = [
+            &request.file_field,
+            &request.filename_field,
+            &request.timestamp_field,
+            &request.min_speakers_field,
+            &request.max_speakers_field,
+            &request.markers_field,
+            &request.metadata_json_field,
+        ];
+        for (entry, name) in self.field_name_entries.iter().zip(field_names) {
+            entry.set_text(name);
+        }
+        set_text_view_pairs(&self.headers_view, &request.headers, ": ");
+        self.response_url_entry.set_text(&request.response.url);
+        self.response_id_entry.set_text(&request.response.id);
+        self.default_check.set_active(
+            index.is_some() && self.default_name.borrow().as_deref() == Some(destination.name.as_str()),
+        );
+        self.update_auth_fields();
+    }
+    
+    /// Show the fields of the selected target and authentication method
+    fn update_auth_fields(&self) {
+        let target = self.target_combo.active_id().unwrap_or_default();
+        let s3 = target == "s3";
+        self.webhook_box.set_visible(target == "webhook");
+        self.webdav_box.set_visible(target == "webdav");
+        self.s3_box.set_visible(s3);
+        self.auth_combo.set_visible(!s3);
+        self.access_key_entry.set_visible(s3);
+        if s3 {
+            self.auth_name_entry.set_visible(false);
+            self.secret_entry.set_visible(true);
+            self.secret_entry.set_placeholder_text(Some("Clé secrète (vide : conserver celle du trousseau)"));
+            return;
+        }
+        self.secret_entry.set_placeholder_text(Some("Secret (vide : conserver celui du trousseau)"));
+        
+        let kind = self.auth_combo.active_id().unwrap_or_default();
+        let parameter_placeholder = match kind.as_str() {
+            "basic" => Some("Nom d'utilisateur"),
+            "header" => Some("Nom de l'en-tête (ex. X-API-Key)"),
+            "hmac" => Some("En-tête de signature (ex. X-Signature)"),
+            _ => None,
+        };
+        self.auth_name_entry.set_visible(parameter_placeholder.is_some());
+        self.auth_name_entry.set_placeholder_text(parameter_placeholder);
+        self.secret_entry.set_visible(kind != "none");
+    }
+}
+
+/// Edit the upload destinations: name, target (webhook, S3 or WebDAV), URL, authentication,
+/// request format and default destination. Secrets go to the keyring when the dialog is applied.
+fn show_destinations_dialog(parent: &gtk4::Dialog, state: &Rc<RefCell<RecorderState>>, settings_button: &Button) {
+    use gtk4::{Dialog, Label, ComboBoxText, Box as GtkBox, ResponseType, Button, Entry, PasswordEntry, TextView, CheckButton};
+    
+    let dialog = Dialog::builder()
+        .title("Destinations d'upload")
+        .transient_for(parent)
+        .modal(true)
+        .default_width(380)
+        .build();
+    
+    let vbox = GtkBox::new(Orientation::Vertical, 8);
+    vbox.set_margin_top(12);
+    vbox.set_margin_bottom(12);
+    vbox.set_margin_start(12);
+    vbox.set_margin_end(12);
+    
+    let picker_row = GtkBox::new(Orientation::Horizontal, 6);
+    let picker = ComboBoxText::new();
+    picker.add_css_class("settings-combo");
+    picker.set_hexpand(true);
+    let add_button = Button::with_label("+");
+    add_button.add_css_class("settings-button");
+    add_button.set_tooltip_text(Some("Ajouter une destination"));
+    let remove_button = Button::with_label("−");
+    remove_button.add_css_class("settings-button");
+    remove_button.set_tooltip_text(Some("Supprimer la destination"));
+    picker_row.append(&picker);
+    picker_row.append(&add_button);
+    picker_row.append(&remove_button);
+    vbox.append(&picker_row);
+    
+    let form = GtkBox::new(Orientation::Vertical, 6);
+    let section_label = |text: &str| {
+        let label = Label::builder()
+            .label(format!("<small>{}</small>", text))
+            .use_markup(true)
+            .halign(gtk4::Align::Start)
+            .margin_top(4)
+            .build();
+        label.add_css_class("settings-label");
+        label
+    };
+    
+    form.append(&section_label("Nom"));
+    let name_entry = Entry::new();
+    name_entry.add_css_class("settings-entry");
+    form.append(&name_entry);
+    
+    form.append(&section_label("Type"));
+    let target_combo = ComboBoxText::new();
+    target_combo.add_css_class("settings-combo");
+    target_combo.append(Some("webhook"), "Webhook (N8N…)");
+    target_combo.append(Some("s3"), "Stockage S3 (AWS, MinIO…)");
+    target_combo.append(Some("webdav"), "WebDAV (Nextcloud…)");
+    form.append(&target_combo);
+    
+    form.append(&section_label("URL"));
+    let url_entry = Entry::new();
+    url_entry.add_css_class("settings-entry");
+    url_entry.set_placeholder_text(Some("https://..."));
+    form.append(&url_entry);
+    
+    form.append(&section_label("Authentification"));
+    let auth_combo = ComboBoxText::new();
+    auth_combo.add_css_class("settings-combo");
+    auth_combo.append(Some("none"), "Aucune");
+    auth_combo.append(Some("bearer"), "Jeton Bearer");
+    auth_combo.append(Some("basic"), "HTTP Basic");
+    auth_combo.append(Some("header"), "Clé API (en-tête)");
+    auth_combo.append(Some("hmac"), "Signature HMAC-SHA256 du corps");
+    form.append(&auth_combo);
+    let auth_name_entry = Entry::new();
+    auth_name_entry.add_css_class("settings-entry");
+    form.append(&auth_name_entry);
+    let access_key_entry = Entry::new();
+    access_key_entry.add_css_class("settings-entry");
+    access_key_entry.set_placeholder_text(Some("Identifiant de clé d'accès"));
+    form.append(&access_key_entry);
+    let secret_entry = PasswordEntry::builder()
+        .show_peek_icon(true)
+        .build();
+    secret_entry.add_css_class("settings-entry");
+    form.append(&secret_entry);
+    
+    let placeholders: Vec<String> = upload_request::PLACEHOLDERS.iter().map(|p| format!("{{{}}}", p)).collect();
+    let placeholders_hint = format!("Variables : {}", placeholders.join(" "));
+    url_entry.set_tooltip_text(Some(&placeholders_hint));
+    
+    let webhook_box = GtkBox::new(Orientation::Vertical, 6);
+    webhook_box.append(&section_label("Champs supplémentaires (nom=valeur, un par ligne)"));
+    let fields_view = TextView::builder()
+        .monospace(true)
+        .height_request(60)
+        .tooltip_text(placeholders_hint.as_str())
+        .build();
+    fields_view.add_css_class("settings-entry");
+    webhook_box.append(&fields_view);
+    
+    // Request shape, for services other than N8N webhooks
+    let request_box = GtkBox::new(Orientation::Vertical, 6);
+    let request_expander = gtk4::Expander::builder()
+        .label("Format de la requête")
+        .child(&request_box)
+        .margin_top(4)
+        .build();
+    request_expander.add_css_class("settings-label");
+    
+    let method_row = GtkBox::new(Orientation::Horizontal, 6);
+    let method_combo = ComboBoxText::new();
+    method_combo.add_css_class("settings-combo");
+    for method in upload_request::HttpMethod::ALL {
+        method_combo.append(Some(method.as_str()), method.as_str());
+    }
+    let body_combo = ComboBoxText::new();
+    body_combo.add_css_class("settings-combo");
+    body_combo.set_hexpand(true);
+    for (mode, label) in upload_request::BodyMode::ALL
+        .into_iter()
+        .zip(["Multipart (formulaire)", "Corps brut (fichier seul)", "JSON (fichier en base64)"])
+    {
+        body_combo.append(Some(mode.id()), label);
+    }
+    method_row.append(&method_combo);
+    method_row.append(&body_combo);
+    request_box.append(&method_row);
+    
+    request_box.append(&section_label("Noms des champs (vide : non envoyé)"));
+    let field_names_grid = gtk4::Grid::builder()
+        .row_spacing(4)
+        .column_spacing(6)
+        .build();
+    let field_name_entries: Vec<Entry> = [
+        "Fichier",
+        "Nom du fichier",
+        "Horodatage",
+        "Locuteurs min",
+        "Locuteurs max",
+        "Marqueurs",
+        "Métadonnées JSON",
+    ]
+    .iter()
+    .enumerate()
+    .map(|(row, label_text)| {
+        let label = Label::builder()
+            .label(*label_text)
+            .halign(gtk4::Align::Start)
+            .build();
+        label.add_css_class("settings-label");
+        let entry = Entry::new();
+        entry.add_css_class("settings-entry");
+        entry.set_hexpand(true);
+        field_names_grid.attach(&label, 0, row as i32, 1, 1);
+        field_names_grid.attach(&entry, 1, row as i32, 1, 1);
+        entry
+    })
+    .collect();
+    field_name_entries[6].set_placeholder_text(Some("Aucun (champs séparés)"));
+    field_name_entries[6].set_tooltip_text(Some("Regroupe les métadonnées dans un objet JSON sous ce nom"));
+    request_box.append(&field_names_grid);
+    
+    request_box.append(&section_label("En-têtes (Nom: valeur, un par ligne)"));
+    let headers_view = TextView::builder()
+        .monospace(true)
+        .height_request(48)
+        .tooltip_text(placeholders_hint.as_str())
+        .build();
+    headers_view.add_css_class("settings-entry");
+    request_box.append(&headers_view);
+    
+    request_box.append(&section_label("Réponse JSON (chemin, ex. data.url ; vide : ignoré)"));
+    let response_grid = gtk4::Grid::builder()
+        .row_spacing(4)
+        .column_spacing(6)
+        .build();
+    let [response_url_entry, response_id_entry] = [(0, "Lien à ouvrir"), (1, "Identifiant")].map(|(row, label_text)| {
+        let label = Label::builder()
+            .label(label_text)
+            .halign(gtk4::Align::Start)
+            .build();
+        label.add_css_class("settings-label");
+        let entry = Entry::new();
+        entry.add_css_class("settings-entry");
+        entry.set_hexpand(true);
+        response_grid.attach(&label, 0, row, 1, 1);
+        response_grid.attach(&entry, 1, row, 1, 1);
+        entry
+    });
+    request_box.append(&response_grid);
+    webhook_box.append(&request_expander);
+    form.append(&webhook_box);
+    
+    let webdav_box = GtkBox::new(Orientation::Vertical, 6);
+    webdav_box.append(&section_label("Dossier (créé s'il n'existe pas)"));
+    let folder_entry = Entry::new();
+    folder_entry.add_css_class("settings-entry");
+    folder_entry.set_tooltip_text(Some(&placeholders_hint));
+    webdav_box.append(&folder_entry);
+    form.append(&webdav_box);
+    
+    let s3_box = GtkBox::new(Orientation::Vertical, 6);
+    let s3_entry = |label: &str, tooltip: Option<&str>| {
+        s3_box.append(&section_label(label));
+        let entry = Entry::new();
+        entry.add_css_class("settings-entry");
+        entry.set_tooltip_text(tooltip);
+        s3_box.append(&entry);
+        entry
+    };
+    let bucket_entry = s3_entry("Bucket", None);
+    let region_entry = s3_entry("Région", Some("us-east-1 pour MinIO"));
+    let key_template_entry = s3_entry("Clé de l'objet", Some(&placeholders_hint));
+    let path_style_check = CheckButton::with_label("Adressage par chemin (URL/bucket/clé, MinIO)");
+    path_style_check.add_css_class("settings-label");
+    s3_box.append(&path_style_check);
+    form.append(&s3_box);
+    
+    form.append(&section_label("Locuteurs par défaut (envoi automatique)"));
+    let speakers_row = GtkBox::new(Orientation::Horizontal, 6);
+    let [min_speakers_spin, max_speakers_spin] = ["Min :", "Max :"].map(|label_text| {
+        let label = Label::new(Some(label_text));
+        label.add_css_class("settings-label");
+        let spin = gtk4::SpinButton::with_range(1.0, 50.0, 1.0);
+        spin.add_css_class("settings-entry");
+        speakers_row.append(&label);
+        speakers_row.append(&spin);
+        spin
+    });
+    form.append(&speakers_row);
+    
+    let default_check = CheckButton::with_label("Destination par défaut");
+    default_check.add_css_class("settings-label");
+    form.append(&default_check);
+    vbox.append(&form);
+    
+    // Keyring problems stay visible here: secrets would otherwise be silently lost
+    let keyring_label = Label::builder()
+        .halign(gtk4::Align::Start)
+        .wrap(true)
+        .max_width_chars(48)
+        .build();
+    keyring_label.add_css_class("settings-error");
+    keyring_label.set_visible(false);
+    vbox.append(&keyring_label);
+    let label = keyring_label.clone();
+    glib::spawn_future_local(async move {
+        if let Ok(Err(e)) = gio::spawn_blocking(keyring::check_available).await {
+            label.set_text(&format!("Trousseau indisponible, les secrets ne seront pas enregistrés : {}", e));
+            label.set_visible(true);
+        }
+    });
+    
+    let state_borrow = state.borrow();
+    let original = state_borrow.upload_destinations.lock().unwrap().clone();
+    let editor = Rc::new(DestinationsEditor {
+        entries: RefCell::new(
+            original
+                .iter()
+                .map(|d| (d.clone(), String::new(), Some(d.name.clone())))
+                .collect(),
+        ),
+        default_name: RefCell::new(state_borrow.default_destination.lock().unwrap().clone()),
+        current: std::cell::Cell::new(None),
+        rebuilding: std::cell::Cell::new(false),
+        picker: picker.clone(),
+        form,
+        name_entry,
+        target_combo: target_combo.clone(),
+        url_entry,
+        auth_combo: auth_combo.clone(),
+        auth_name_entry,
+        access_key_entry,
+        secret_entry,
+        webhook_box,
+        fields_view,
+        method_combo,
+        body_combo,
+        field_name_entries,
+        headers_view,
+        response_url_entry,
+        response_id_entry,
+        webdav_box,
+        folder_entry,
+        s3_box,
+        bucket_entry,
+        region_entry,
+        key_template_entry,
+        path_style_check,
+        min_speakers_spin,
+        max_speakers_spin,
+        default_check,
+    });
+    drop(state_borrow);
+    editor.show(if original.is_empty() { None } else { Some(0) });
+    
+    let editor_clone = Rc::clone(&editor);
+    picker.connect_changed(move |picker| {
+        if editor_clone.rebuilding.get() {
+            return;
+        }
+        editor_clone.store_current();
+        editor_clone.show(picker.active().map(|idx| idx as usize));
+    });
+    let editor_clone = Rc::clone(&editor);
+    auth_combo.connect_changed(move |_| editor_clone.update_auth_fields());
+    let editor_clone = Rc::clone(&editor);
+    target_combo.connect_changed(move |_| editor_clone.update_auth_fields());
+    let editor_clone = Rc::clone(&editor);
+    add_button.connect_clicked(move |_| {
+        editor_clone.store_current();
+        let count = {
+            let mut entries = editor_clone.entries.borrow_mut();
+            let name = format!("Destination {}", entries.len() + 1);
+            entries.push((
+                UploadDestination {
+                    name,
+                    ..Default::default()
+                },
+                String::new(),
+                None,
+            ));
+            entries.len()
+        };
+        editor_clone.show(Some(count - 1));
+    });
+    let editor_clone = Rc::clone(&editor);
+    remove_button.connect_clicked(move |_| {
+        let Some(index) = editor_clone.current.get() else {
+            return;
+        };
+        let (removed, _, _) = editor_clone.entries.borrow_mut().remove(index);
+        if editor_clone.default_name.borrow().as_deref() == Some(removed.name.as_str()) {
+            *editor_clone.default_name.borrow_mut() = None;
+        }
+        editor_clone.current.set(None);
+        let remaining = editor_clone.entries.borrow().len();
+        editor_clone.show(if remaining == 0 { None } else { Some(index.min(remaining - 1)) });
+    });
+    
+    let button_box = GtkBox::new(Orientation::Horizontal, 12);
+    button_box.set_halign(gtk4::Align::End);
+    button_box.set_margin_top(8);
+    for (label, response, suggested) in [
+        ("Cancel", ResponseType::Cancel, false),
+        ("Apply", ResponseType::Apply, true),
+    ] {
+        let button = Button::with_label(label);
+        button.add_css_class("settings-button");
+        if suggested {
+            button.add_css_class("suggested-action");
+        }
+        let button_dialog = dialog.clone();
+        button.connect_clicked(move |_| button_dialog.response(response));
+        button_box.append(&button);
+    }
+    vbox.append(&button_box);
+    dialog.content_area().append(&vbox);
+    
+    let state = Rc::clone(state);
+    let settings_button = settings_button.clone();
+    dialog.connect_response(move |dialog, response| {
+        if response != ResponseType::Apply {
+            dialog.close();
+            return;
+        }
+        editor.store_current();
+        let entries = editor.entries.borrow().clone();
+        
+        // Names identify destinations (default, queue, command line): they must be unique
+        let mut names = std::collections::HashSet::new();
+        for (destination, _, _) in &entries {
+            if destination.name.is_empty() || destination.url.is_empty() {
+                show_notification("Erreur", "Chaque destination doit avoir un nom et une URL");
+                return;
+            }
+            if !names.insert(destination.name.clone()) {
+                show_notification("Erreur", &format!("Nom de destination en double : {}", destination.name));
+                return;
+            }
+            let validation = match &destination.target {
+                UploadTarget::Webhook => destination.request.validate(&destination.url, &destination.fields),
+                UploadTarget::S3(s3) => s3.validate(&destination.url),
+                UploadTarget::Webdav(webdav) => webdav.validate(&destination.url),
+            };
+            if let Err(e) = validation {
+                show_notification("Erreur", &format!("Destination {} : {}", destination.name, e));
+                return;
+            }
+        }
+        
+        // Secrets are keyed by destination name and follow renames
+        let mut changes = SecretChanges::default();
+        for (destination, secret, stored_name) in &entries {
+            if !destination.needs_secret() {
+                continue;
+            }
+            if !secret.is_empty() {
+                changes.typed.push((destination.name.clone(), secret.clone()));
+            } else if let Some(stored_name) = stored_name.as_ref().filter(|name| **name != destination.name) {
+                changes.renamed.push((destination.name.clone(), stored_name.clone()));
+            }
+        }
+        // Drop the secrets of names no destination needing one has any more
+        for old in &original {
+            let still_used = entries
+                .iter()
+                .any(|(destination, _, _)| destination.name == old.name && destination.needs_secret());
+            if old.needs_secret() && !still_used {
+                changes.removed.push(old.name.clone());
+            }
+        }
         
-        file_chooser.show();
+        // The keyring may prompt for its password: wait for it off the main thread,
+        // and only keep the destinations once their secrets are saved
+        dialog.set_sensitive(false);
+        let destinations: Vec<UploadDestination> = entries.into_iter().map(|(destination, _, _)| destination).collect();
+        let default_name = editor.default_name.borrow().clone();
+        let dialog = dialog.clone();
+        let keyring_label = keyring_label.clone();
+        let state = Rc::clone(&state);
+        let settings_button = settings_button.clone();
+        glib::spawn_future_local(async move {
+            let result = gio::spawn_blocking(move || changes.apply())
+                .await
+                .unwrap_or_else(|_| Err("le trousseau a planté".to_string()));
+            dialog.set_sensitive(true);
+            if let Err(e) = result {
+                keyring_label.set_text(&e);
+                keyring_label.set_visible(true);
+                return;
+            }
+            println!("Upload destinations updated ({})", destinations.len());
+            settings_button.set_label(&format!("Destinations ({})…", destinations.len()));
+            let state = state.borrow();
+            *state.upload_destinations.lock().unwrap() = destinations;
+            *state.default_destination.lock().unwrap() = default_name;
+            state.save_config();
+            dialog.close();
+        });
     });
     
-    save_dir_box.append(&save_dir_entry);
-    save_dir_box.append(&browse_button);
-    vbox.append(&save_dir_box);
+    dialog.present();
+}
+
+/// Keyring changes of the destinations dialog
+#[derive(Default)]
+struct SecretChanges {
+    /// (destination, secret typed in the dialog)
+    typed: Vec<(String, String)>,
+    /// (destination, name its secret is stored under) for renamed destinations
+    renamed: Vec<(String, String)>,
+    /// Names whose secret is no longer needed
+    removed: Vec<String>,
+}
+
+impl SecretChanges {
+    /// Make the changes (blocking: the keyring may prompt). The error is shown to the user.
+    fn apply(self) -> Result<(), String> {
+        // All read before any is written: two destinations may have swapped names
+        let mut secrets = self.typed;
+        for (name, stored_name) in self.renamed {
+            match upload_auth::load_secret(&stored_name) {
+                Ok(Some(secret)) => secrets.push((name, secret)),
+                Ok(None) => {}
+                Err(e) => {
+                    eprintln!("Failed to read upload secret: {}", e);
+                    return Err(format!("Secret de {} illisible dans le trousseau : {}", stored_name, e));
+                }
+            }
+        }
+        for (name, secret) in &secrets {
+            if let Err(e) = upload_auth::store_secret(name, secret) {
+                eprintln!("Failed to store upload secret: {}", e);
+                return Err(format!("Secret de {} non enregistré dans le trousseau : {}", name, e));
+            }
+        }
+        for name in &self.removed {
+            upload_auth::clear_secret(name);
+        }
+        Ok(())
+    }
+}
+
+/// Settings row with a checkbox enabling a value edited in a spin button
+fn build_toggle_spin_row(
+    label: &str,
+    enabled: bool,
+    (min, max, step): (f64, f64, f64),
+    value: f64,
+    unit: &str,
+) -> (gtk4::Box, gtk4::CheckButton, gtk4::SpinButton) {
+    let row = gtk4::Box::new(Orientation::Horizontal, 6);
     
-    // N8N Upload section
-    let n8n_label = Label::builder()
-        .label("<small>Upload N8N</small>")
-        .use_markup(true)
+    let check = gtk4::CheckButton::new();
+    check.set_active(enabled);
+    let text_label = gtk4::Label::builder()
+        .label(label)
         .halign(gtk4::Align::Start)
-        .margin_top(10)
+        .hexpand(true)
         .build();
-    n8n_label.add_css_class("settings-label");
-    vbox.append(&n8n_label);
+    text_label.add_css_class("settings-label");
     
-    let n8n_enabled_box = GtkBox::new(Orientation::Horizontal, 6);
-    let n8n_enabled_check = gtk4::CheckButton::new();
-    n8n_enabled_check.set_active(*state_borrow.n8n_enabled.lock().unwrap());
-    let n8n_enabled_label = Label::builder()
-        .label("Activer l'upload vers N8N")
-        .halign(gtk4::Align::Start)
+    let spin = gtk4::SpinButton::with_range(min, max, step);
+    spin.set_digits(0);
+    spin.set_value(value);
+    spin.add_css_class("settings-entry");
+    spin.set_sensitive(enabled);
+    let unit_label = gtk4::Label::new(Some(unit));
+    unit_label.add_css_class("settings-label");
+    
+    let spin_clone = spin.clone();
+    check.connect_toggled(move |check| {
+        spin_clone.set_sensitive(check.is_active());
+    });
+    
+    row.append(&check);
+    row.append(&text_label);
+    row.append(&spin);
+    row.append(&unit_label);
+    (row, check, spin)
+}
+
+/// Stop the running recording (saving or discarding it), then quit once the encode
+/// and upload threads are done
+fn quit_application(
+    app: &Application,
+    window: &ApplicationWindow,
+    state: &Rc<RefCell<RecorderState>>,
+    record_button: &Button,
+    save_recording: bool,
+) {
+    QUITTING.store(true, Ordering::SeqCst);
+    window.hide();
+    
+    if state.borrow().recording {
+        if save_recording {
+            println!("Saving the current recording before quitting");
+        } else {
+            println!("Discarding the current recording");
+            // Streams first: a callback running in between would leave a fragment to save
+            state.borrow_mut().discard_captured_audio();
+        }
+        record_button.emit_clicked();
+    }
+    
+    let app = app.clone();
+    let mut announced = false;
+    glib::timeout_add_local(Duration::from_millis(100), move || {
+        let pending = BACKGROUND_TASKS.load(Ordering::SeqCst);
+        if pending > 0 {
+            if !announced {
+                println!("Waiting for {} background task(s) before quitting", pending);
+                announced = true;
+            }
+            return glib::ControlFlow::Continue;
+        }
+        println!("Quitting");
+        app.quit();
+        glib::ControlFlow::Break
+    });
+}
+
+thread_local! {
+    // Job list of the open upload queue dialog, rebuilt when the queue changes
+    static UPLOAD_QUEUE_LIST: RefCell<Option<glib::WeakRef<gtk4::Box>>> = const { RefCell::new(None) };
+}
+
+/// List the queued uploads, with their retry state, and let the user retry or remove them
+fn show_upload_queue_dialog(parent: &ApplicationWindow) {
+    use gtk4::{Dialog, Box as GtkBox, ScrolledWindow};
+    
+    parent.present();
+    let dialog = Dialog::builder()
+        .title("Envois en attente")
+        .transient_for(parent)
+        .default_width(440)
+        .default_height(300)
         .build();
-    n8n_enabled_label.add_css_class("settings-label");
-    n8n_enabled_box.append(&n8n_enabled_check);
-    n8n_enabled_box.append(&n8n_enabled_label);
-    vbox.append(&n8n_enabled_box);
     
-    let n8n_endpoint_label = Label::builder()
-        .label("<small>URL de l'endpoint N8N</small>")
-        .use_markup(true)
-        .halign(gtk4::Align::Start)
-        .margin_top(4)
+    let list = GtkBox::new(Orientation::Vertical, 8);
+    list.set_margin_top(12);
+    list.set_margin_bottom(12);
+    list.set_margin_start(12);
+    list.set_margin_end(12);
+    let scrolled = ScrolledWindow::builder()
+        .child(&list)
+        .vexpand(true)
         .build();
-    n8n_endpoint_label.add_css_class("settings-label");
-    vbox.append(&n8n_endpoint_label);
+    dialog.content_area().append(&scrolled);
+    
+    UPLOAD_QUEUE_LIST.with(|current| *current.borrow_mut() = Some(list.downgrade()));
+    refresh_upload_queue_list(&upload_queue::jobs());
+    
+    dialog.present();
+}
+
+/// Rebuild the job list of the upload queue dialog, if it is open
+fn refresh_upload_queue_list(jobs: &[upload_queue::UploadJob]) {
+    use gtk4::{Label, Box as GtkBox};
     
-    let n8n_endpoint_entry = gtk4::Entry::new();
-    n8n_endpoint_entry.add_css_class("settings-entry");
-    n8n_endpoint_entry.set_placeholder_text(Some("https://..."));
-    if let Some(endpoint) = state_borrow.n8n_endpoint.lock().unwrap().clone() {
-        n8n_endpoint_entry.set_text(&endpoint);
+    let Some(list) = UPLOAD_QUEUE_LIST.with(|current| current.borrow().as_ref().and_then(|list| list.upgrade())) else {
+        return;
+    };
+    while let Some(child) = list.first_child() {
+        list.remove(&child);
     }
-    n8n_endpoint_entry.set_sensitive(*state_borrow.n8n_enabled.lock().unwrap());
     
-    let endpoint_entry_clone = n8n_endpoint_entry.clone();
-    n8n_enabled_check.connect_toggled(move |check| {
-        endpoint_entry_clone.set_sensitive(check.is_active());
-    });
+    if jobs.is_empty() {
+        let empty_label = Label::new(Some("Aucun envoi en attente"));
+        empty_label.add_css_class("settings-label");
+        list.append(&empty_label);
+        return;
+    }
     
-    vbox.append(&n8n_endpoint_entry);
+    for job in jobs {
+        let row = GtkBox::new(Orientation::Horizontal, 8);
+        let text = GtkBox::new(Orientation::Vertical, 2);
+        text.set_hexpand(true);
+        
+        let name_label = Label::builder()
+            .label(format!("📁 {} → {}", job.file_name(), job.destination_label()))
+            .halign(gtk4::Align::Start)
+            .build();
+        name_label.add_css_class("settings-label");
+        text.append(&name_label);
+        
+        let status = if job.uploading {
+            "Envoi en cours…".to_string()
+        } else if job.attempts == 0 {
+            "En attente".to_string()
+        } else {
+            let next_attempt = chrono::DateTime::from_timestamp(job.next_attempt, 0)
+                .map(|time| time.with_timezone(&Local).format("%H:%M:%S").to_string())
+                .unwrap_or_default();
+            format!(
+                "{} échec(s), prochain essai à {}\n{}",
+                job.attempts,
+                next_attempt,
+                job.last_error.as_deref().unwrap_or("")
+            )
+        };
+        let status_label = Label::builder()
+            .label(format!("<small>{}</small>", glib::markup_escape_text(&status)))
+            .use_markup(true)
+            .wrap(true)
+            .halign(gtk4::Align::Start)
+            .build();
+        status_label.add_css_class("settings-label");
+        text.append(&status_label);
+        row.append(&text);
+        
+        let retry_button = Button::with_label("Réessayer");
+        retry_button.add_css_class("settings-button");
+        retry_button.set_valign(gtk4::Align::Center);
+        retry_button.set_sensitive(!job.uploading);
+        let id = job.id;
+        retry_button.connect_clicked(move |_| upload_queue::retry_now(id));
+        row.append(&retry_button);
+        
+        let remove_button = Button::with_label("Retirer");
+        remove_button.add_css_class("settings-button");
+        remove_button.set_valign(gtk4::Align::Center);
+        remove_button.set_tooltip_text(Some("Retirer de la file (le fichier local est conservé)"));
+        remove_button.connect_clicked(move |_| upload_queue::remove(id));
+        row.append(&remove_button);
+        
+        list.append(&row);
+    }
+}
+
+/// Ask whether to keep the running recording before quitting
+fn show_quit_dialog(
+    app: &Application,
+    window: &ApplicationWindow,
+    state: &Rc<RefCell<RecorderState>>,
+    record_button: &Button,
+) {
+    use gtk4::{Dialog, Label, Box as GtkBox, ResponseType, Button};
     
-    let n8n_save_locally_box = GtkBox::new(Orientation::Horizontal, 6);
-    let n8n_save_locally_check = gtk4::CheckButton::new();
-    n8n_save_locally_check.set_active(*state_borrow.save_locally.lock().unwrap());
-    let n8n_save_locally_label = Label::builder()
-        .label("Conserver le fichier localement après upload")
-        .halign(gtk4::Align::Start)
+    window.present();
+    let dialog = Dialog::builder()
+        .title("Quitter")
+        .modal(true)
+        .transient_for(window)
+        .default_width(360)
         .build();
-    n8n_save_locally_label.add_css_class("settings-label");
-    n8n_save_locally_box.append(&n8n_save_locally_check);
-    n8n_save_locally_box.append(&n8n_save_locally_label);
-    n8n_save_locally_box.set_margin_top(4);
-    vbox.append(&n8n_save_locally_box);
     
-    drop(state_borrow); // Release borrow before showing dialog
+    let content_area = dialog.content_area();
+    let vbox = GtkBox::new(Orientation::Vertical, 10);
+    vbox.set_margin_top(16);
+    vbox.set_margin_bottom(12);
+    vbox.set_margin_start(16);
+    vbox.set_margin_end(16);
+    
+    let message_label = Label::builder()
+        .label("Un enregistrement est en cours. Le sauvegarder avant de quitter ?")
+        .wrap(true)
+        .halign(gtk4::Align::Start)
+        .build();
+    message_label.add_css_class("settings-label");
+    vbox.append(&message_label);
     
+    let button_box = GtkBox::new(Orientation::Horizontal, 8);
+    button_box.set_halign(gtk4::Align::End);
+    button_box.set_margin_top(8);
+    for (label, response, suggested) in [
+        ("Annuler", ResponseType::Cancel, false),
+        ("Abandonner", ResponseType::Reject, false),
+        ("Sauvegarder", ResponseType::Accept, true),
+    ] {
+        let button = Button::with_label(label);
+        button.add_css_class("settings-button");
+        if suggested {
+            button.add_css_class("suggested-action");
+        }
+        let button_dialog = dialog.clone();
+        button.connect_clicked(move |_| button_dialog.response(response));
+        button_box.append(&button);
+    }
+    vbox.append(&button_box);
     content_area.append(&vbox);
-    content_area.append(&button_box);
     
-    let state_clone = Rc::clone(state);
+    let app = app.clone();
+    let window = window.clone();
+    let state = Rc::clone(state);
+    let record_button = record_button.clone();
     dialog.connect_response(move |dialog, response| {
-        if response == ResponseType::Apply || response == ResponseType::Cancel {
-            if response == ResponseType::Apply {
-                let mut state = state_clone.borrow_mut();
-                
-                // Update microphone
-                if let Some(id) = mic_combo.active_id() {
-                    if let Ok(idx) = id.parse::<usize>() {
-                        state.selected_mic_index = idx;
-                        println!("Microphone updated to index: {}", idx);
-                    }
-                }
-                
-                // Update loopback
-                if let Some(id) = loopback_combo.active_id() {
-                    if id == "none" {
-                        state.selected_loopback_index = None;
-                        println!("Loopback disabled");
-                    } else if let Ok(idx) = id.parse::<usize>() {
-                        state.selected_loopback_index = Some(idx);
-                        println!("Loopback updated to index: {}", idx);
-                    }
-                }
-                
-                // Update save directory
-                let dir_text = save_dir_entry.text().to_string();
-                if dir_text.is_empty() || dir_text == "Dossier courant" {
-                    *state.save_directory.lock().unwrap() = None;
-                } else {
-                    // Expand ~ to home directory
-                    let expanded_path = if dir_text.starts_with("~/") {
-                        if let Some(home) = dirs::home_dir() {
-                            home.join(&dir_text[2..]).to_string_lossy().to_string()
-                        } else {
-                            dir_text
-                        }
-                    } else {
-                        dir_text
-                    };
-                    *state.save_directory.lock().unwrap() = Some(expanded_path);
-                    println!("Save directory updated");
-                }
-                
-                // Update N8N settings
-                *state.n8n_enabled.lock().unwrap() = n8n_enabled_check.is_active();
-                
-                let endpoint_text = n8n_endpoint_entry.text().to_string();
-                if endpoint_text.is_empty() {
-                    *state.n8n_endpoint.lock().unwrap() = None;
-                } else {
-                    *state.n8n_endpoint.lock().unwrap() = Some(endpoint_text);
-                    println!("N8N endpoint updated");
-                }
-                
-                *state.save_locally.lock().unwrap() = n8n_save_locally_check.is_active();
-                
-                // Save config
-                state.save_config();
-            }
-            dialog.close();
+        dialog.close();
+        match response {
+            ResponseType::Accept => quit_application(&app, &window, &state, &record_button, true),
+            ResponseType::Reject => quit_application(&app, &window, &state, &record_button, false),
+            _ => println!("Quit cancelled"),
         }
     });
     
@@ -1722,10 +4660,10 @@ fn show_settings_dialog(parent: &ApplicationWindow, state: &Rc<RefCell<RecorderS
 }
 
 fn show_upload_dialog(parent: Option<&ApplicationWindow>, request: UploadDialogRequest) {
-    use gtk4::{Dialog, Label, Box as GtkBox, ResponseType, Button, SpinButton, Adjustment, Entry};
+    use gtk4::{Dialog, Label, Box as GtkBox, ResponseType, Button, SpinButton, Adjustment, ComboBoxText};
     
     let dialog = Dialog::builder()
-        .title("Envoyer vers N8N")
+        .title("Envoyer l'enregistrement")
         .modal(true)
         .default_width(400)
         .default_height(240)
@@ -1755,20 +4693,43 @@ fn show_upload_dialog(parent: Option<&ApplicationWindow>, request: UploadDialogR
     file_label.add_css_class("settings-label");
     vbox.append(&file_label);
     
-    // N8N endpoint URL (overridable)
-    let endpoint_label = Label::builder()
-        .label("URL endpoint N8N:")
+    // Destination picker, the default destination preselected
+    let destination_label = Label::builder()
+        .label("Destination:")
         .halign(gtk4::Align::Start)
         .margin_top(8)
         .build();
-    endpoint_label.add_css_class("settings-label");
-    vbox.append(&endpoint_label);
+    destination_label.add_css_class("settings-label");
+    vbox.append(&destination_label);
+    
+    let destination_combo = ComboBoxText::new();
+    destination_combo.add_css_class("settings-combo");
+    for (idx, destination) in request.destinations.iter().enumerate() {
+        destination_combo.append(Some(&idx.to_string()), &destination.name);
+    }
+    let default_index = find_destination(&request.destinations, request.default_destination.as_deref(), None)
+        .and_then(|default| request.destinations.iter().position(|d| d == default))
+        .unwrap_or(0);
+    destination_combo.set_active(Some(default_index as u32));
+    vbox.append(&destination_combo);
     
-    let endpoint_entry = Entry::new();
-    endpoint_entry.set_text(&request.endpoint);
-    endpoint_entry.set_placeholder_text(Some("https://..."));
-    endpoint_entry.add_css_class("settings-entry");
-    vbox.append(&endpoint_entry);
+    let destination_url_label = Label::builder()
+        .halign(gtk4::Align::Start)
+        .ellipsize(gtk4::pango::EllipsizeMode::Middle)
+        .build();
+    destination_url_label.add_css_class("settings-label");
+    vbox.append(&destination_url_label);
+    let destinations = request.destinations.clone();
+    let update_url_label = move |combo: &ComboBoxText| {
+        let url = combo
+            .active()
+            .and_then(|idx| destinations.get(idx as usize))
+            .map(|d| d.location())
+            .unwrap_or_default();
+        destination_url_label.set_markup(&format!("<small>{}</small>", glib::markup_escape_text(&url)));
+    };
+    update_url_label(&destination_combo);
+    destination_combo.connect_changed(update_url_label);
     
     // Speaker count section
     let speaker_label = Label::builder()
@@ -1845,6 +4806,46 @@ fn show_upload_dialog(parent: Option<&ApplicationWindow>, request: UploadDialogR
     
     vbox.append(&speaker_box);
     
+    // The estimate is only a rough guess: applied on request, as a range around it
+    if let Some(estimate) = request.estimated_speakers {
+        let estimate_box = GtkBox::new(Orientation::Horizontal, 12);
+        let estimate_label = Label::builder()
+            .label(format!("Estimation: {} locuteur{}", estimate, if estimate > 1 { "s" } else { "" }))
+            .build();
+        estimate_label.add_css_class("settings-label");
+        estimate_box.append(&estimate_label);
+        
+        let estimate_button = Button::with_label("Utiliser");
+        estimate_button.add_css_class("settings-button");
+        estimate_button.set_tooltip_text(Some("Estimé sur l'enregistrement, à corriger au besoin"));
+        let min_spin_clone = min_spin.clone();
+        let max_spin_clone = max_spin.clone();
+        estimate_button.connect_clicked(move |_| {
+            max_spin_clone.set_value((estimate + 1) as f64);
+            min_spin_clone.set_value(estimate.saturating_sub(1).max(1) as f64);
+        });
+        estimate_box.append(&estimate_button);
+        vbox.append(&estimate_box);
+    }
+    
+    // Speaker counts last sent to the chosen destination, or its default ones
+    let destinations = request.destinations.clone();
+    let last_speakers = request.last_speakers.clone();
+    let min_spin_clone = min_spin.clone();
+    let max_spin_clone = max_spin.clone();
+    let set_default_speakers = move |combo: &ComboBoxText| {
+        if let Some(destination) = combo.active().and_then(|idx| destinations.get(idx as usize)) {
+            let speakers = last_speakers
+                .get(&destination.name)
+                .copied()
+                .unwrap_or(destination.default_speakers);
+            max_spin_clone.set_value(speakers.max as f64);
+            min_spin_clone.set_value(speakers.min as f64);
+        }
+    };
+    set_default_speakers(&destination_combo);
+    destination_combo.connect_changed(set_default_speakers);
+    
     // Button box
     let button_box = GtkBox::new(Orientation::Horizontal, 12);
     button_box.set_halign(gtk4::Align::End);
@@ -1876,40 +4877,23 @@ fn show_upload_dialog(parent: Option<&ApplicationWindow>, request: UploadDialogR
     
     dialog.connect_response(move |dialog, response| {
         if response == ResponseType::Accept {
-            let endpoint = endpoint_entry.text().to_string();
-            
-            // Validate endpoint URL
-            if endpoint.trim().is_empty() {
-                show_notification("Erreur", "L'URL de l'endpoint N8N est requise");
+            let Some(destination) = destination_combo
+                .active()
+                .and_then(|idx| request.destinations.get(idx as usize))
+            else {
+                show_notification("Erreur", "Choisissez une destination");
                 return;
-            }
+            };
             
             let min_speakers = min_spin.value() as u32;
             let max_speakers = max_spin.value() as u32;
-            let file_path = file_path.clone();
+            send_ui_message(UiMessage::RememberSpeakers(
+                destination.name.clone(),
+                SpeakerCounts { min: min_speakers, max: max_speakers },
+            ));
             
-            // Perform upload in background thread
-            std::thread::spawn(move || {
-                match upload_to_n8n_sync(&file_path, &endpoint, min_speakers, max_speakers) {
-                    Ok(_) => {
-                        println!("Upload to N8N succeeded");
-                        show_notification("Upload réussi", "Le fichier a été envoyé à N8N");
-                        
-                        // Delete local file if not configured to keep it
-                        if !save_locally {
-                            if let Err(e) = std::fs::remove_file(&file_path) {
-                                eprintln!("Failed to delete local file: {}", e);
-                            } else {
-                                println!("Local file deleted (save_locally=false)");
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("Upload to N8N failed: {}", e);
-                        show_notification("Échec de l'upload", &format!("Erreur: {}", e));
-                    }
-                }
-            });
+            // Sent (and retried on failure) by the upload queue
+            upload_queue::enqueue(&file_path, destination, min_speakers, max_speakers, !save_locally);
             dialog.close();
         } else {
             println!("Upload cancelled by user");
@@ -1920,3 +4904,115 @@ fn show_upload_dialog(parent: Option<&ApplicationWindow>, request: UploadDialogR
     
     dialog.present();
 }
+
+/// Toast announcing an automatic upload. The upload is queued when the countdown ends,
+/// unless it is sent now, changed in the upload dialog or cancelled first.
+fn show_upload_countdown(parent: &ApplicationWindow, request: UploadDialogRequest, seconds: u32) {
+    use gtk4::{Label, Box as GtkBox, Button, Window};
+    
+    let Some(destination) = find_destination(&request.destinations, request.default_destination.as_deref(), None) else {
+        return;
+    };
+    let file_name = std::path::Path::new(&request.file_path)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("recording.ogg")
+        .to_string();
+    let destination_name = destination.name.clone();
+    
+    let toast = Window::builder()
+        .title("Envoi automatique")
+        .transient_for(parent)
+        .resizable(false)
+        .default_width(360)
+        .build();
+    let vbox = GtkBox::new(Orientation::Vertical, 10);
+    vbox.set_margin_top(12);
+    vbox.set_margin_bottom(12);
+    vbox.set_margin_start(16);
+    vbox.set_margin_end(16);
+    
+    let message_label = Label::builder()
+        .halign(gtk4::Align::Start)
+        .wrap(true)
+        .build();
+    message_label.add_css_class("settings-label");
+    vbox.append(&message_label);
+    let update_message = move |remaining: u32| {
+        message_label.set_text(&format!(
+            "📁 {}\nEnvoi vers {} dans {} s",
+            file_name, destination_name, remaining
+        ));
+    };
+    update_message(seconds);
+    
+    // Whichever comes first takes the request: a button, closing the toast or the timer
+    let request = Rc::new(RefCell::new(Some(request)));
+    let remaining = std::cell::Cell::new(seconds);
+    let request_clone = Rc::clone(&request);
+    let toast_clone = toast.clone();
+    glib::timeout_add_seconds_local(1, move || {
+        if request_clone.borrow().is_none() {
+            return glib::ControlFlow::Break;
+        }
+        remaining.set(remaining.get().saturating_sub(1));
+        if remaining.get() > 0 {
+            update_message(remaining.get());
+            return glib::ControlFlow::Continue;
+        }
+        if let Some(request) = request_clone.take() {
+            request.enqueue_with_defaults();
+        }
+        toast_clone.close();
+        glib::ControlFlow::Break
+    });
+    
+    let button_box = GtkBox::new(Orientation::Horizontal, 12);
+    button_box.set_halign(gtk4::Align::End);
+    
+    let cancel_button = Button::with_label("Annuler");
+    cancel_button.add_css_class("settings-button");
+    let toast_clone = toast.clone();
+    cancel_button.connect_clicked(move |_| toast_clone.close());
+    
+    let edit_button = Button::with_label("Modifier…");
+    edit_button.add_css_class("settings-button");
+    let request_clone = Rc::clone(&request);
+    let toast_clone = toast.clone();
+    let parent_clone = parent.clone();
+    edit_button.connect_clicked(move |_| {
+        if let Some(request) = request_clone.take() {
+            show_upload_dialog(Some(&parent_clone), request);
+        }
+        toast_clone.close();
+    });
+    
+    let send_button = Button::with_label("Envoyer");
+    send_button.add_css_class("settings-button");
+    send_button.add_css_class("suggested-action");
+    let request_clone = Rc::clone(&request);
+    let toast_clone = toast.clone();
+    send_button.connect_clicked(move |_| {
+        if let Some(request) = request_clone.take() {
+            request.enqueue_with_defaults();
+        }
+        toast_clone.close();
+    });
+    
+    button_box.append(&cancel_button);
+    button_box.append(&edit_button);
+    button_box.append(&send_button);
+    vbox.append(&button_box);
+    toast.set_child(Some(&vbox));
+    
+    // Closing the toast (or Annuler) keeps the recording local only, like the dialog
+    toast.connect_close_request(move |_| {
+        if request.take().is_some() {
+            println!("Automatic upload cancelled by user");
+            show_notification("Upload annulé", "L'enregistrement n'a pas été envoyé");
+        }
+        glib::Propagation::Proceed
+    });
+    
+    toast.present();
+}
@@ -0,0 +1,107 @@
+// Time-stamped markers set during a recording. They are written as chapter tags in
+// the Ogg file and as sidecar files next to it: `<name>.markers.json` for tools and
+// uploads, `<name>.vtt` (WebVTT chapters) for media players.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Marker {
+    /// Offset from the start of the recording, paused time excluded
+    pub offset_seconds: f64,
+    pub note: String,
+}
+
+impl Marker {
+    /// Note, or a numbered default title for markers without one
+    pub fn title(&self, index: usize) -> String {
+        if self.note.trim().is_empty() {
+            format!("Marqueur {}", index + 1)
+        } else {
+            self.note.trim().to_string()
+        }
+    }
+}
+
+/// HH:MM:SS.mmm, as used by both WebVTT and Vorbis chapter tags
+pub fn format_timestamp(seconds: f64) -> String {
+    let total_ms = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        total_ms / 3_600_000,
+        total_ms / 60_000 % 60,
+        total_ms / 1000 % 60,
+        total_ms % 1000
+    )
+}
+
+/// Vorbis comment chapter tags (CHAPTER001, CHAPTER001NAME, ...)
+pub fn chapter_tags(markers: &[Marker]) -> Vec<(String, String)> {
+    markers
+        .iter()
+        .enumerate()
+        .flat_map(|(i, marker)| {
+            let key = format!("CHAPTER{:03}", i + 1);
+            [
+                (key.clone(), format_timestamp(marker.offset_seconds)),
+                (format!("{}NAME", key), marker.title(i)),
+            ]
+        })
+        .collect()
+}
+
+fn sidecar_path(audio_path: &str, extension: &str) -> PathBuf {
+    Path::new(audio_path).with_extension(extension)
+}
+
+fn json_path(audio_path: &str) -> PathBuf {
+    sidecar_path(audio_path, "markers.json")
+}
+
+fn vtt_path(audio_path: &str) -> PathBuf {
+    sidecar_path(audio_path, "vtt")
+}
+
+/// WebVTT chapters: each marker lasts until the next one, the last until the end
+fn to_webvtt(markers: &[Marker], duration_seconds: f64) -> String {
+    let mut vtt = String::from("WEBVTT\n");
+    for (i, marker) in markers.iter().enumerate() {
+        let end = markers
+            .get(i + 1)
+            .map(|next| next.offset_seconds)
+            .unwrap_or(duration_seconds)
+            .max(marker.offset_seconds);
+        vtt.push_str(&format!(
+            "\n{}\n{} --> {}\n{}\n",
+            i + 1,
+            format_timestamp(marker.offset_seconds),
+            format_timestamp(end),
+            marker.title(i)
+        ));
+    }
+    vtt
+}
+
+/// Write the JSON and WebVTT sidecars for a saved recording
+pub fn write_sidecars(audio_path: &str, markers: &[Marker], duration_seconds: f64) -> std::io::Result<()> {
+    let json = serde_json::to_string_pretty(markers)?;
+    std::fs::write(json_path(audio_path), json)?;
+    std::fs::write(vtt_path(audio_path), to_webvtt(markers, duration_seconds))
+}
+
+/// Markers JSON of a recording, if it has any
+pub fn read_json(audio_path: &str) -> Option<String> {
+    std::fs::read_to_string(json_path(audio_path)).ok()
+}
+
+/// Delete the sidecars along with the recording
+pub fn remove_sidecars(audio_path: &str) {
+    for path in [json_path(audio_path), vtt_path(audio_path)] {
+        if path.exists() {
+            if let Err(e) = std::fs::remove_file(&path) {
+                eprintln!("Failed to delete {}: {}", path.display(), e);
+            }
+        }
+    }
+}
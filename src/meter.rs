@@ -0,0 +1,152 @@
+// Level meter state shared between the capture callbacks and the UI:
+// RMS and peak in dBFS, peak-hold and a latching clip indicator.
+
+use std::time::{Duration, Instant};
+
+pub const METER_FLOOR_DB: f32 = -60.0;
+// Samples at or above this magnitude count as clipping
+const CLIP_LEVEL: f32 = 0.999;
+const PEAK_HOLD_TIME: Duration = Duration::from_millis(1500);
+const PEAK_HOLD_DECAY_DB_PER_SEC: f32 = 20.0;
+// Ballistics of the displayed values (per update)
+const RMS_SMOOTHING: f32 = 0.6;
+const PEAK_FALL_DB_PER_SEC: f32 = 30.0;
+
+pub fn to_dbfs(linear: f32) -> f32 {
+    if linear > 0.0 {
+        (20.0 * linear.log10()).max(METER_FLOOR_DB)
+    } else {
+        METER_FLOOR_DB
+    }
+}
+
+pub struct LevelMeter {
+    pub rms_db: f32,
+    pub peak_db: f32,
+    pub peak_hold_db: f32,
+    pub clipped: bool,
+    peak_hold_since: Instant,
+    last_update: Instant,
+}
+
+impl Default for LevelMeter {
+    fn default() -> Self {
+        let now = Instant::now();
+        Self {
+            rms_db: METER_FLOOR_DB,
+            peak_db: METER_FLOOR_DB,
+            peak_hold_db: METER_FLOOR_DB,
+            clipped: false,
+            peak_hold_since: now,
+            last_update: now,
+        }
+    }
+}
+
+impl LevelMeter {
+    /// Feed one block of samples. Must be called with the signal before any clamping,
+    /// otherwise overs cannot be detected.
+    pub fn update(&mut self, block: &[f32]) {
+        if block.is_empty() {
+            return;
+        }
+
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_update).as_secs_f32();
+        self.last_update = now;
+
+        let sum: f32 = block.iter().map(|&s| s * s).sum();
+        let rms_db = to_dbfs((sum / block.len() as f32).sqrt());
+        let peak = block.iter().fold(0.0f32, |acc, &s| acc.max(s.abs()));
+        let peak_db = to_dbfs(peak);
+
+        if peak >= CLIP_LEVEL {
+            self.clipped = true;
+        }
+
+        self.rms_db = RMS_SMOOTHING * self.rms_db + (1.0 - RMS_SMOOTHING) * rms_db;
+        self.peak_db = peak_db.max(self.peak_db - PEAK_FALL_DB_PER_SEC * elapsed);
+
+        if peak_db >= self.peak_hold_db {
+            self.peak_hold_db = peak_db;
+            self.peak_hold_since = now;
+        } else if now.duration_since(self.peak_hold_since) > PEAK_HOLD_TIME {
+            self.peak_hold_db = (self.peak_hold_db - PEAK_HOLD_DECAY_DB_PER_SEC * elapsed).max(peak_db);
+        }
+    }
+
+    /// Clear the latched clip indicator
+    pub fn reset_clip(&mut self) {
+        self.clipped = false;
+    }
+
+    /// Drop the levels back to silence. The clip indicator stays latched
+    /// so an over is still visible after the recording stops.
+    pub fn reset(&mut self) {
+        let clipped = self.clipped;
+        *self = Self::default();
+        self.clipped = clipped;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clip_latches_before_any_clamp() {
+        let mut meter = LevelMeter::default();
+        meter.update(&[0.5, -0.99]);
+        assert!(!meter.clipped);
+        // An over as the capture delivers it, before the signal is clamped to ±1
+        meter.update(&[0.2, -1.7]);
+        assert!(meter.clipped);
+        assert_eq!(meter.peak_db, to_dbfs(1.7));
+        // Latched through quiet blocks
+        meter.update(&[0.01; 64]);
+        assert!(meter.clipped);
+
+        let mut meter = LevelMeter::default();
+        meter.update(&[CLIP_LEVEL]);
+        assert!(meter.clipped);
+    }
+
+    #[test]
+    fn reset_keeps_the_clip_and_reset_clip_clears_it() {
+        let mut meter = LevelMeter::default();
+        meter.update(&[1.0, -1.0]);
+        meter.reset();
+        assert!(meter.clipped);
+        assert_eq!(meter.rms_db, METER_FLOOR_DB);
+        assert_eq!(meter.peak_db, METER_FLOOR_DB);
+        assert_eq!(meter.peak_hold_db, METER_FLOOR_DB);
+
+        meter.reset_clip();
+        assert!(!meter.clipped);
+    }
+
+    #[test]
+    fn peak_hold_holds_then_decays() {
+        let mut meter = LevelMeter::default();
+        meter.update(&[0.5]);
+        let held = meter.peak_hold_db;
+        assert_eq!(held, to_dbfs(0.5));
+
+        // Quieter blocks within the hold time leave it in place
+        meter.update(&[0.05]);
+        assert_eq!(meter.peak_hold_db, held);
+
+        // Past the hold time it falls at its decay rate, one second since the last block
+        let now = Instant::now();
+        meter.peak_hold_since = now - PEAK_HOLD_TIME - Duration::from_millis(100);
+        meter.last_update = now - Duration::from_secs(1);
+        meter.update(&[0.05]);
+        let fallen = held - meter.peak_hold_db;
+        assert!((fallen - PEAK_HOLD_DECAY_DB_PER_SEC).abs() < 1.0, "fell {} dB", fallen);
+
+        // Never below the current peak
+        meter.last_update = Instant::now() - Duration::from_secs(10);
+        meter.update(&[0.05]);
+        assert_eq!(meter.peak_hold_db, to_dbfs(0.05));
+    }
+}
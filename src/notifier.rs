@@ -1,18 +1,28 @@
 use std::sync::mpsc::{channel, Sender};
 use std::thread;
 
+struct Notification {
+    title: String,
+    body: String,
+    // URL opened by an "Ouvrir" action
+    link: Option<String>,
+}
+
 pub struct Notifier {
-    sender: Sender<(String, String)>,
+    sender: Sender<Notification>,
 }
 
 impl Notifier {
     pub fn new() -> Self {
-        let (sender, receiver) = channel::<(String, String)>();
+        let (sender, receiver) = channel::<Notification>();
         
         // Spawn a dedicated thread for notifications
         thread::spawn(move || {
-            while let Ok((title, body)) = receiver.recv() {
-                Self::send_notification(&title, &body);
+            while let Ok(notification) = receiver.recv() {
+                match notification.link {
+                    Some(link) => Self::send_notification_with_link(notification.title, notification.body, link),
+                    None => Self::send_notification(&notification.title, &notification.body),
+                }
             }
         });
         
@@ -20,7 +30,20 @@ impl Notifier {
     }
     
     pub fn notify(&self, title: &str, body: &str) {
-        let _ = self.sender.send((title.to_string(), body.to_string()));
+        let _ = self.sender.send(Notification {
+            title: title.to_string(),
+            body: body.to_string(),
+            link: None,
+        });
+    }
+    
+    /// Notification with an action opening `link` in the default application
+    pub fn notify_with_link(&self, title: &str, body: &str, link: &str) {
+        let _ = self.sender.send(Notification {
+            title: title.to_string(),
+            body: body.to_string(),
+            link: Some(link.to_string()),
+        });
     }
     
     fn send_notification(title: &str, body: &str) {
@@ -40,4 +63,33 @@ impl Notifier {
             Err(e) => eprintln!("✗ Failed to send notification: {}", e),
         }
     }
+    
+    fn send_notification_with_link(title: String, body: String, link: String) {
+        // notify-send waits for the action until the notification closes, so it gets
+        // its own thread
+        thread::spawn(move || {
+            let output = std::process::Command::new("notify-send")
+                .arg("-a")
+                .arg("Audio Recorder")
+                .arg("-i")
+                .arg("audio-input-microphone")
+                .arg("-A")
+                .arg("open=Ouvrir")
+                .arg(&title)
+                .arg(&body)
+                .output();
+            match output {
+                Ok(output) if output.status.success() => {
+                    println!("✓ Notification sent: {} - {}", title, body);
+                    if String::from_utf8_lossy(&output.stdout).trim() == "open" {
+                        if let Err(e) = std::process::Command::new("xdg-open").arg(&link).spawn() {
+                            eprintln!("Failed to open {}: {}", link, e);
+                        }
+                    }
+                }
+                // notify-send older than 0.7.10 has no actions: show the link instead
+                _ => Self::send_notification(&title, &format!("{}\n{}", body, link)),
+            }
+        });
+    }
 }
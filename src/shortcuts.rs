@@ -0,0 +1,253 @@
+// Global keyboard shortcuts through the XDG GlobalShortcuts desktop portal.
+// The desktop may ask the user to confirm or change the preferred triggers.
+// Where the portal is not available (X11 sessions, older desktops), bind a desktop
+// shortcut to `audio-recorder --toggle` / `--pause` / `--marker` instead: the
+// command is forwarded to the running instance.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+
+use gtk4::gio;
+use gtk4::glib;
+use gtk4::glib::prelude::*;
+use gtk4::glib::variant::ObjectPath;
+
+const PORTAL_BUS_NAME: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const SHORTCUTS_INTERFACE: &str = "org.freedesktop.portal.GlobalShortcuts";
+const REQUEST_INTERFACE: &str = "org.freedesktop.portal.Request";
+const SESSION_INTERFACE: &str = "org.freedesktop.portal.Session";
+
+pub const ACTION_TOGGLE_RECORDING: &str = "toggle-recording";
+pub const ACTION_PAUSE: &str = "pause";
+pub const ACTION_MARKER: &str = "add-marker";
+
+/// Preferred triggers, in the XDG shortcuts format (e.g. "CTRL+ALT+R").
+/// An empty trigger leaves the action unbound.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShortcutBindings {
+    pub toggle_recording: String,
+    pub pause: String,
+    pub marker: String,
+}
+
+impl ShortcutBindings {
+    fn entries(&self) -> [(&'static str, &'static str, &str); 3] {
+        [
+            (ACTION_TOGGLE_RECORDING, "Start/stop recording", &self.toggle_recording),
+            (ACTION_PAUSE, "Pause/resume recording", &self.pause),
+            (ACTION_MARKER, "Add a marker", &self.marker),
+        ]
+    }
+}
+
+struct PortalSession {
+    connection: gio::DBusConnection,
+    handle: String,
+    activated: gio::SignalSubscriptionId,
+}
+
+static SESSION: Mutex<Option<PortalSession>> = Mutex::new(None);
+// Bumped on every registration so a slow portal reply for older bindings is discarded
+static GENERATION: AtomicU32 = AtomicU32::new(0);
+
+/// (Re)register the bindings with the portal. `on_activated` receives the action id
+/// and runs on the main loop.
+pub fn register(bindings: &ShortcutBindings, on_activated: fn(&str)) {
+    close_session();
+    let generation = GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+
+    let shortcuts: Vec<(String, HashMap<String, glib::Variant>)> = bindings
+        .entries()
+        .iter()
+        .filter(|(_, _, trigger)| !trigger.trim().is_empty())
+        .map(|(id, description, trigger)| {
+            let mut properties = HashMap::new();
+            properties.insert("description".to_string(), description.to_variant());
+            properties.insert("preferred_trigger".to_string(), trigger.trim().to_variant());
+            (id.to_string(), properties)
+        })
+        .collect();
+    if shortcuts.is_empty() {
+        return;
+    }
+
+    let connection = match gio::bus_get_sync(gio::BusType::Session, None::<&gio::Cancellable>) {
+        Ok(connection) => connection,
+        Err(e) => {
+            eprintln!("Global shortcuts unavailable (no session bus): {}", e);
+            return;
+        }
+    };
+
+    let mut options = HashMap::new();
+    options.insert("session_handle_token".to_string(), format!("audio_recorder_{}", generation).to_variant());
+    let create_connection = connection.clone();
+    portal_call(&connection, "CreateSession", (options,).to_variant(), move |results| {
+        let Some(handle) = results.get("session_handle").and_then(|v| v.str().map(str::to_owned)) else {
+            eprintln!("Global shortcuts portal returned no session");
+            return;
+        };
+        if GENERATION.load(Ordering::SeqCst) != generation {
+            close_portal_session(&create_connection, &handle);
+            return;
+        }
+        let Ok(session_path) = ObjectPath::try_from(handle.clone()) else {
+            return;
+        };
+
+        // Subscribe before binding so no activation is missed
+        let session_for_signal = handle.clone();
+        let activated = create_connection.signal_subscribe(
+            Some(PORTAL_BUS_NAME),
+            Some(SHORTCUTS_INTERFACE),
+            Some("Activated"),
+            Some(PORTAL_PATH),
+            None,
+            gio::DBusSignalFlags::NONE,
+            move |_, _, _, _, _, parameters| {
+                let session = parameters.try_child_value(0).and_then(|v| v.str().map(str::to_owned));
+                let action = parameters.try_child_value(1).and_then(|v| v.str().map(str::to_owned));
+                if let (Some(session), Some(action)) = (session, action) {
+                    if session == session_for_signal {
+                        println!("Global shortcut activated: {}", action);
+                        on_activated(&action);
+                    }
+                }
+            },
+        );
+        *SESSION.lock().unwrap() = Some(PortalSession {
+            connection: create_connection.clone(),
+            handle,
+            activated,
+        });
+
+        let parameters = (session_path, shortcuts, String::new(), HashMap::<String, glib::Variant>::new());
+        portal_call(&create_connection, "BindShortcuts", parameters.to_variant(), |results| {
+            let bound = results
+                .get("shortcuts")
+                .and_then(|v| v.get::<Vec<(String, HashMap<String, glib::Variant>)>>())
+                .unwrap_or_default();
+            for (id, properties) in bound {
+                let trigger = properties
+                    .get("trigger_description")
+                    .and_then(|v| v.str().map(str::to_owned))
+                    .unwrap_or_default();
+                println!("Global shortcut bound: {} = {}", id, trigger);
+            }
+        });
+    });
+}
+
+/// Call a portal method whose result comes back as a Request::Response signal.
+/// The options dict must be the last parameter; a handle_token is added to it.
+fn portal_call<F>(connection: &gio::DBusConnection, method: &str, parameters: glib::Variant, on_response: F)
+where
+    F: FnOnce(HashMap<String, glib::Variant>) + 'static,
+{
+    static TOKEN: AtomicU32 = AtomicU32::new(0);
+    let token = format!("audio_recorder_req_{}", TOKEN.fetch_add(1, Ordering::SeqCst));
+
+    // The request object path is predictable: subscribe to its Response before the call
+    let sender = connection
+        .unique_name()
+        .map(|name| name.trim_start_matches(':').replace('.', "_"))
+        .unwrap_or_default();
+    let request_path = format!("{}/request/{}/{}", PORTAL_PATH, sender, token);
+
+    let parameters = with_handle_token(&parameters, &token);
+    let on_response = RefCell::new(Some(on_response));
+    let subscription: Rc<RefCell<Option<gio::SignalSubscriptionId>>> = Rc::new(RefCell::new(None));
+    let subscription_for_signal = Rc::clone(&subscription);
+    let id = connection.signal_subscribe(
+        Some(PORTAL_BUS_NAME),
+        Some(REQUEST_INTERFACE),
+        Some("Response"),
+        Some(&request_path),
+        None,
+        gio::DBusSignalFlags::NONE,
+        move |connection, _, _, _, _, parameters| {
+            if let Some(id) = subscription_for_signal.borrow_mut().take() {
+                connection.signal_unsubscribe(id);
+            }
+            let Some((response, results)) = parameters.get::<(u32, HashMap<String, glib::Variant>)>() else {
+                return;
+            };
+            match response {
+                0 => {
+                    if let Some(callback) = on_response.borrow_mut().take() {
+                        callback(results);
+                    }
+                }
+                1 => println!("Global shortcuts request cancelled by the user"),
+                _ => eprintln!("Global shortcuts request failed (response {})", response),
+            }
+        },
+    );
+    *subscription.borrow_mut() = Some(id);
+
+    let method_name = method.to_string();
+    let connection_for_error = connection.clone();
+    connection.call(
+        Some(PORTAL_BUS_NAME),
+        PORTAL_PATH,
+        SHORTCUTS_INTERFACE,
+        method,
+        Some(&parameters),
+        None,
+        gio::DBusCallFlags::NONE,
+        -1,
+        None::<&gio::Cancellable>,
+        move |result| {
+            if let Err(e) = result {
+                eprintln!(
+                    "Global shortcuts portal unavailable ({}: {}). Bind `audio-recorder --toggle` in the desktop settings instead.",
+                    method_name, e
+                );
+                if let Some(id) = subscription.borrow_mut().take() {
+                    connection_for_error.signal_unsubscribe(id);
+                }
+            }
+        },
+    );
+}
+
+/// Copy a parameter tuple, adding handle_token to its trailing a{sv} options
+fn with_handle_token(parameters: &glib::Variant, token: &str) -> glib::Variant {
+    let count = parameters.n_children();
+    let children = (0..count).map(|i| {
+        let child = parameters.child_value(i);
+        if i + 1 < count {
+            return child;
+        }
+        let mut options = child.get::<HashMap<String, glib::Variant>>().unwrap_or_default();
+        options.insert("handle_token".to_string(), token.to_variant());
+        options.to_variant()
+    });
+    glib::Variant::tuple_from_iter(children)
+}
+
+fn close_portal_session(connection: &gio::DBusConnection, handle: &str) {
+    connection.call(
+        Some(PORTAL_BUS_NAME),
+        handle,
+        SESSION_INTERFACE,
+        "Close",
+        None,
+        None,
+        gio::DBusCallFlags::NONE,
+        -1,
+        None::<&gio::Cancellable>,
+        |_| {},
+    );
+}
+
+fn close_session() {
+    if let Some(session) = SESSION.lock().unwrap().take() {
+        session.connection.signal_unsubscribe(session.activated);
+        close_portal_session(&session.connection, &session.handle);
+    }
+}
@@ -0,0 +1,330 @@
+// Rough on-device estimate of the number of distinct speakers in a recording, offered
+// in the upload dialog. The speech is cut into segments of a second and a half whose
+// frames are described by MFCCs. Segments are then merged bottom-up while a single
+// Gaussian explains two clusters better than two Gaussians do, by the Bayesian
+// information criterion. Music, crosstalk or very short turns throw it off: it is a
+// suggestion for the user to correct, not a diarization.
+
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+
+const FRAME_MS: u32 = 32;
+const NUM_FILTERS: usize = 24;
+// Cepstral coefficients c1..c12; c0 (the frame energy) says nothing about the voice
+const NUM_COEFFS: usize = 12;
+const MIN_FREQUENCY: f32 = 100.0;
+const MAX_FREQUENCY: f32 = 7000.0;
+// Frames this far above the noise floor (10th percentile of the frame energies) are speech
+const SPEECH_MARGIN_DB: f32 = 12.0;
+// About 1.5 s of 32 ms frames
+const SEGMENT_FRAMES: usize = 47;
+// Segments clustered at most, picked evenly over the recording: merging is cubic
+const MAX_SEGMENTS: usize = 300;
+// About two and a half minutes of speech: with less, the guesses are all over the place
+const MIN_SEGMENTS: usize = 100;
+// Weight of the BIC model-size penalty: the textbook 1.0 splits a voice into several
+// clusters as its intonation changes
+const BIC_PENALTY_WEIGHT: f64 = 2.0;
+// Clusters holding less of the speech are left out (noises, interjections)
+const MIN_SPEAKER_SHARE: f64 = 0.1;
+
+type Features = [f64; NUM_COEFFS];
+
+/// Frames of one or more segments, as the sufficient statistics of a diagonal Gaussian
+#[derive(Clone)]
+struct Cluster {
+    frames: f64,
+    sum: Features,
+    sum_sq: Features,
+}
+
+impl Cluster {
+    fn new(frames: &[Features]) -> Self {
+        let mut cluster = Cluster {
+            frames: frames.len() as f64,
+            sum: [0.0; NUM_COEFFS],
+            sum_sq: [0.0; NUM_COEFFS],
+        };
+        for frame in frames {
+            for (k, &value) in frame.iter().enumerate() {
+                cluster.sum[k] += value;
+                cluster.sum_sq[k] += value * value;
+            }
+        }
+        cluster
+    }
+
+    fn merged(&self, other: &Cluster) -> Cluster {
+        let mut merged = self.clone();
+        merged.frames += other.frames;
+        for k in 0..NUM_COEFFS {
+            merged.sum[k] += other.sum[k];
+            merged.sum_sq[k] += other.sum_sq[k];
+        }
+        merged
+    }
+
+    /// Log-determinant of the (diagonal) covariance
+    fn log_det(&self) -> f64 {
+        (0..NUM_COEFFS)
+            .map(|k| {
+                let mean = self.sum[k] / self.frames;
+                (self.sum_sq[k] / self.frames - mean * mean).max(1e-6).ln()
+            })
+            .sum()
+    }
+
+    /// BIC difference between modelling both clusters with one Gaussian and with two:
+    /// negative when they are better merged (probably the same speaker)
+    fn delta_bic(&self, other: &Cluster) -> f64 {
+        let merged = self.merged(other);
+        let likelihood_gain =
+            0.5 * (merged.frames * merged.log_det() - self.frames * self.log_det() - other.frames * other.log_det());
+        // The second Gaussian costs a mean and a variance per coefficient
+        let penalty = BIC_PENALTY_WEIGHT * 0.5 * (2 * NUM_COEFFS) as f64 * merged.frames.ln();
+        likelihood_gain - penalty
+    }
+}
+
+/// Estimated number of speakers, or None with too little speech to tell
+pub fn estimate_count(samples: &[f32], sample_rate: u32) -> Option<u32> {
+    let frames = frame_features(samples, sample_rate);
+    if frames.is_empty() {
+        return None;
+    }
+
+    let mut energies: Vec<f32> = frames.iter().map(|(energy_db, _)| *energy_db).collect();
+    energies.sort_by(|a, b| a.total_cmp(b));
+    let speech_threshold = energies[energies.len() / 10] + SPEECH_MARGIN_DB;
+
+    // Speech frames of the segments that are mostly speech
+    let segments: Vec<Vec<Features>> = frames
+        .chunks(SEGMENT_FRAMES)
+        .map(|segment| {
+            segment
+                .iter()
+                .filter(|(energy_db, _)| *energy_db > speech_threshold)
+                .map(|(_, features)| *features)
+                .collect::<Vec<_>>()
+        })
+        .filter(|speech| speech.len() * 2 >= SEGMENT_FRAMES)
+        .collect();
+    if segments.len() < MIN_SEGMENTS {
+        return None;
+    }
+    let stride = segments.len().div_ceil(MAX_SEGMENTS);
+    let mut clusters: Vec<Option<Cluster>> = segments
+        .iter()
+        .step_by(stride)
+        .map(|speech| Some(Cluster::new(speech)))
+        .collect();
+
+    // Agglomerative clustering, always merging the best pair first
+    let count = clusters.len();
+    let mut distances = vec![vec![f64::INFINITY; count]; count];
+    for i in 0..count {
+        for j in i + 1..count {
+            if let (Some(a), Some(b)) = (&clusters[i], &clusters[j]) {
+                distances[i][j] = a.delta_bic(b);
+            }
+        }
+    }
+    loop {
+        let mut best = (f64::INFINITY, 0, 0);
+        for (i, row) in distances.iter().enumerate() {
+            for (j, &distance) in row.iter().enumerate().skip(i + 1) {
+                if distance < best.0 {
+                    best = (distance, i, j);
+                }
+            }
+        }
+        let (distance, i, j) = best;
+        if distance >= 0.0 {
+            break;
+        }
+
+        let (Some(a), Some(b)) = (clusters[i].take(), clusters[j].take()) else {
+            break;
+        };
+        let merged = a.merged(&b);
+        for k in 0..count {
+            distances[k][j] = f64::INFINITY;
+            distances[j][k] = f64::INFINITY;
+            if k == i {
+                continue;
+            }
+            let distance = clusters[k].as_ref().map_or(f64::INFINITY, |other| merged.delta_bic(other));
+            distances[i.min(k)][i.max(k)] = distance;
+        }
+        clusters[i] = Some(merged);
+    }
+
+    let clusters: Vec<Cluster> = clusters.into_iter().flatten().collect();
+    let speech_frames: f64 = clusters.iter().map(|cluster| cluster.frames).sum();
+    let speakers = clusters
+        .iter()
+        .filter(|cluster| cluster.frames / speech_frames >= MIN_SPEAKER_SHARE)
+        .count();
+    Some(speakers.max(1) as u32)
+}
+
+/// Energy (dB) and MFCCs of consecutive frames
+fn frame_features(samples: &[f32], sample_rate: u32) -> Vec<(f32, Features)> {
+    let frame_size = (sample_rate * FRAME_MS / 1000) as usize;
+    let fft_size = frame_size.next_power_of_two();
+    let bins = fft_size / 2 + 1;
+    let fft = FftPlanner::<f32>::new().plan_fft_forward(fft_size);
+    let window: Vec<f32> = (0..frame_size)
+        .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / frame_size as f32).cos())
+        .collect();
+    let filters = mel_filters(sample_rate, fft_size, bins);
+
+    let mut buffer = vec![Complex::new(0.0f32, 0.0); fft_size];
+    samples
+        .chunks_exact(frame_size)
+        .map(|frame| {
+            buffer.iter_mut().for_each(|slot| *slot = Complex::new(0.0, 0.0));
+            for (slot, (&sample, &weight)) in buffer.iter_mut().zip(frame.iter().zip(&window)) {
+                *slot = Complex::new(sample * weight, 0.0);
+            }
+            fft.process(&mut buffer);
+
+            let power: Vec<f32> = buffer[..bins].iter().map(|bin| bin.norm_sqr()).collect();
+            let log_energies: Vec<f64> = filters
+                .iter()
+                .map(|filter| {
+                    let energy: f32 = filter.iter().map(|&(bin, weight)| power[bin] * weight).sum();
+                    (energy.max(1e-10) as f64).ln()
+                })
+                .collect();
+            let energy_db = 10.0 * power.iter().sum::<f32>().max(1e-10).log10();
+
+            // DCT-II of the log filter energies
+            let mut features = [0.0; NUM_COEFFS];
+            for (k, coefficient) in features.iter_mut().enumerate() {
+                *coefficient = log_energies
+                    .iter()
+                    .enumerate()
+                    .map(|(m, &log_energy)| {
+                        log_energy * (std::f64::consts::PI * (k + 1) as f64 * (m as f64 + 0.5) / NUM_FILTERS as f64).cos()
+                    })
+                    .sum();
+            }
+            (energy_db, features)
+        })
+        .collect()
+}
+
+/// Triangular filters evenly spaced on the mel scale, as (bin, weight) lists
+fn mel_filters(sample_rate: u32, fft_size: usize, bins: usize) -> Vec<Vec<(usize, f32)>> {
+    let to_mel = |hz: f32| 2595.0 * (1.0 + hz / 700.0).log10();
+    let to_hz = |mel: f32| 700.0 * (10f32.powf(mel / 2595.0) - 1.0);
+    let low = to_mel(MIN_FREQUENCY);
+    let high = to_mel(MAX_FREQUENCY.min(sample_rate as f32 / 2.0));
+    let bin_hz = sample_rate as f32 / fft_size as f32;
+    let edges: Vec<f32> = (0..NUM_FILTERS + 2)
+        .map(|i| to_hz(low + (high - low) * i as f32 / (NUM_FILTERS + 1) as f32) / bin_hz)
+        .collect();
+
+    edges
+        .windows(3)
+        .map(|edge| {
+            let (start, center, end) = (edge[0], edge[1], edge[2]);
+            (start.ceil() as usize..=(end.floor() as usize).min(bins - 1))
+                .map(|bin| {
+                    let position = bin as f32;
+                    let weight = if position <= center {
+                        (position - start) / (center - start)
+                    } else {
+                        (end - position) / (end - center)
+                    };
+                    (bin, weight.max(0.0))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 16000;
+
+    /// Deterministic xorshift noise in [0, 1)
+    struct Noise(u64);
+
+    impl Noise {
+        fn next(&mut self) -> f32 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % 1_000_000) as f32 / 1_000_000.0
+        }
+    }
+
+    /// A tone source standing in for a voice: harmonics of `pitch` weighted by
+    /// `harmonics`, with a wobbling pitch and a level pulsing like syllables
+    fn tone_source(out: &mut Vec<f32>, seconds: f32, pitch: f32, harmonics: &[f32], noise: &mut Noise) {
+        let mut phase = 0.0f32;
+        for i in 0..(seconds * RATE as f32) as usize {
+            let t = i as f32 / RATE as f32;
+            phase += pitch * (1.0 + 0.05 * (2.0 * std::f32::consts::PI * 3.0 * t).sin()) / RATE as f32;
+            let tone: f32 = harmonics
+                .iter()
+                .enumerate()
+                .map(|(k, weight)| weight * (2.0 * std::f32::consts::PI * (k + 1) as f32 * phase).sin())
+                .sum();
+            let syllable = (std::f32::consts::PI * (t * 4.0 % 1.0)).sin().max(0.1);
+            out.push(0.2 * syllable * tone + 0.002 * (noise.next() - 0.5));
+        }
+    }
+
+    fn pause(out: &mut Vec<f32>, seconds: f32, noise: &mut Noise) {
+        out.extend((0..(seconds * RATE as f32) as usize).map(|_| 0.001 * (noise.next() - 0.5)));
+    }
+
+    /// Four minutes of turns of 2 to 8 s taken at random by the sources
+    fn conversation(sources: &[(f32, &[f32])], seed: u64) -> Vec<f32> {
+        let mut noise = Noise(seed);
+        let mut samples = Vec::new();
+        while samples.len() < 240 * RATE as usize {
+            let (pitch, harmonics) = sources[(noise.next() * sources.len() as f32) as usize % sources.len()];
+            tone_source(&mut samples, 2.0 + 6.0 * noise.next(), pitch, harmonics, &mut noise);
+            pause(&mut samples, 0.3 + 0.7 * noise.next(), &mut noise);
+        }
+        samples
+    }
+
+    const LOW_DARK: (f32, &[f32]) = (110.0, &[1.0, 0.8, 0.6, 0.3, 0.1]);
+    const HIGH_BRIGHT: (f32, &[f32]) = (260.0, &[0.3, 0.5, 1.0, 0.9, 0.7, 0.6, 0.5]);
+
+    #[test]
+    fn short_input_gives_no_estimate() {
+        assert_eq!(estimate_count(&[], RATE), None);
+        // Less than a frame
+        assert_eq!(estimate_count(&[0.1; 100], RATE), None);
+        // A minute of speech is too little to tell
+        let mut noise = Noise(3);
+        let mut samples = Vec::new();
+        tone_source(&mut samples, 60.0, LOW_DARK.0, LOW_DARK.1, &mut noise);
+        assert_eq!(estimate_count(&samples, RATE), None);
+        // So is a long silence
+        let mut samples = Vec::new();
+        pause(&mut samples, 240.0, &mut noise);
+        assert_eq!(estimate_count(&samples, RATE), None);
+    }
+
+    #[test]
+    fn single_source_is_one_speaker() {
+        assert_eq!(estimate_count(&conversation(&[LOW_DARK], 7), RATE), Some(1));
+    }
+
+    #[test]
+    fn two_distinct_sources_are_two_speakers() {
+        for seed in [1, 42] {
+            let samples = conversation(&[LOW_DARK, HIGH_BRIGHT], seed);
+            assert_eq!(estimate_count(&samples, RATE), Some(2), "seed {}", seed);
+        }
+    }
+}
@@ -0,0 +1,163 @@
+// Live spectrum / spectrogram analysis. The capture callbacks only copy their latest
+// samples into a small ring buffer (`SpectrumTap`); the FFT runs on the UI thread
+// (`SpectrumView`) so the recording path gets no extra work or latency.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+
+pub const FFT_SIZE: usize = 2048;
+pub const NUM_BANDS: usize = 64;
+pub const SPECTROGRAM_COLUMNS: usize = 200;
+pub const MIN_DB: f32 = -90.0;
+const MIN_FREQUENCY: f32 = 30.0;
+const MAX_FREQUENCY: f32 = 16000.0;
+// Falling speed of the spectrum display between frames
+const SPECTRUM_DECAY_DB: f32 = 3.0;
+
+/// Shared between a capture callback and the UI
+pub struct SpectrumTap {
+    pub enabled: bool,
+    pub sample_rate: u32,
+    channels: usize,
+    samples: VecDeque<f32>,
+}
+
+impl Default for SpectrumTap {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sample_rate: 48000,
+            channels: 1,
+            samples: VecDeque::with_capacity(FFT_SIZE),
+        }
+    }
+}
+
+impl SpectrumTap {
+    pub fn configure(&mut self, sample_rate: u32, channels: u16) {
+        self.sample_rate = sample_rate;
+        self.channels = channels.max(1) as usize;
+        self.samples.clear();
+    }
+
+    /// Keep the most recent FFT_SIZE mono samples (interleaved input is downmixed)
+    pub fn push(&mut self, interleaved: &[f32]) {
+        if !self.enabled {
+            return;
+        }
+        for frame in interleaved.chunks(self.channels) {
+            if self.samples.len() == FFT_SIZE {
+                self.samples.pop_front();
+            }
+            self.samples.push_back(frame.iter().sum::<f32>() / frame.len() as f32);
+        }
+    }
+
+    pub fn snapshot(&self) -> Vec<f32> {
+        self.samples.iter().copied().collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+}
+
+/// Analysis state for one source, owned by the UI
+pub struct SpectrumView {
+    fft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    /// Current spectrum in dB per log-spaced band
+    pub bands: Vec<f32>,
+    /// Most recent spectra, oldest first
+    pub spectrogram: VecDeque<Vec<f32>>,
+}
+
+impl Default for SpectrumView {
+    fn default() -> Self {
+        let fft = FftPlanner::<f32>::new().plan_fft_forward(FFT_SIZE);
+        let window = (0..FFT_SIZE)
+            .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / FFT_SIZE as f32).cos())
+            .collect();
+        Self {
+            fft,
+            window,
+            bands: vec![MIN_DB; NUM_BANDS],
+            spectrogram: VecDeque::with_capacity(SPECTROGRAM_COLUMNS),
+        }
+    }
+}
+
+impl SpectrumView {
+    pub fn clear(&mut self) {
+        self.bands.iter_mut().for_each(|b| *b = MIN_DB);
+        self.spectrogram.clear();
+    }
+
+    /// Analyse the latest samples and append a spectrogram column
+    pub fn update(&mut self, samples: &[f32], sample_rate: u32) {
+        if samples.len() < FFT_SIZE {
+            return;
+        }
+
+        let mut buffer: Vec<Complex<f32>> = samples[samples.len() - FFT_SIZE..]
+            .iter()
+            .zip(&self.window)
+            .map(|(&s, &w)| Complex::new(s * w, 0.0))
+            .collect();
+        self.fft.process(&mut buffer);
+
+        // Hann window coherent gain is 0.5: a full-scale sine reads 0 dB
+        let scale = 2.0 / (FFT_SIZE as f32 * 0.5);
+        let bin_hz = sample_rate as f32 / FFT_SIZE as f32;
+        let max_frequency = MAX_FREQUENCY.min(sample_rate as f32 / 2.0);
+        let ratio = (max_frequency / MIN_FREQUENCY).powf(1.0 / NUM_BANDS as f32);
+
+        let mut column = Vec::with_capacity(NUM_BANDS);
+        for band in 0..NUM_BANDS {
+            let low = MIN_FREQUENCY * ratio.powi(band as i32);
+            let high = low * ratio;
+            let first_bin = ((low / bin_hz) as usize).max(1);
+            let last_bin = ((high / bin_hz) as usize).max(first_bin).min(FFT_SIZE / 2);
+            let magnitude = buffer[first_bin..=last_bin]
+                .iter()
+                .fold(0.0f32, |acc, bin| acc.max(bin.norm() * scale));
+            column.push((20.0 * magnitude.max(1e-9).log10()).max(MIN_DB));
+        }
+
+        for (displayed, &value) in self.bands.iter_mut().zip(&column) {
+            *displayed = value.max(*displayed - SPECTRUM_DECAY_DB);
+        }
+
+        if self.spectrogram.len() == SPECTROGRAM_COLUMNS {
+            self.spectrogram.pop_front();
+        }
+        self.spectrogram.push_back(column);
+    }
+}
+
+/// Colour map for the spectrogram: dark blue -> magenta -> orange -> pale yellow
+pub fn heat_color(db: f32) -> (u8, u8, u8) {
+    let t = ((db - MIN_DB) / -MIN_DB).clamp(0.0, 1.0);
+    let stops = [
+        (0.0, (10.0, 12.0, 40.0)),
+        (0.35, (120.0, 30.0, 130.0)),
+        (0.7, (240.0, 120.0, 40.0)),
+        (1.0, (255.0, 250.0, 190.0)),
+    ];
+    for pair in stops.windows(2) {
+        let (t0, c0) = pair[0];
+        let (t1, c1) = pair[1];
+        if t <= t1 {
+            let f = (t - t0) / (t1 - t0);
+            return (
+                (c0.0 + (c1.0 - c0.0) * f) as u8,
+                (c0.1 + (c1.1 - c0.1) * f) as u8,
+                (c0.2 + (c1.2 - c0.2) * f) as u8,
+            );
+        }
+    }
+    (255, 250, 190)
+}
@@ -0,0 +1,175 @@
+// Authentication of webhook uploads. The method (and non-secret parts such as a user
+// name or header name) is stored with each upload destination in the config; the token,
+// password, API key or HMAC key lives in the desktop keyring (Secret Service), keyed by
+// the destination name.
+
+use std::io::Read;
+
+use gtk4::glib;
+use serde::{Deserialize, Serialize};
+
+use crate::keyring;
+
+// Keyring attributes identifying the secret of a destination
+const KEYRING_APPLICATION: &str = "audio-recorder";
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum AuthMethod {
+    #[default]
+    None,
+    /// `Authorization: Bearer <token>`
+    Bearer,
+    /// HTTP Basic, the password is the secret
+    Basic { username: String },
+    /// API key sent as the value of a custom header
+    Header { name: String },
+    /// HMAC-SHA256 of the request body, sent as `sha256=<hex>` in the given header
+    Hmac { header: String },
+}
+
+impl AuthMethod {
+    pub fn needs_secret(&self) -> bool {
+        !matches!(self, AuthMethod::None)
+    }
+
+    /// Short id of the method and its non-secret parameter (user or header name)
+    pub fn kind(&self) -> (&'static str, &str) {
+        match self {
+            AuthMethod::None => ("none", ""),
+            AuthMethod::Bearer => ("bearer", ""),
+            AuthMethod::Basic { username } => ("basic", username),
+            AuthMethod::Header { name } => ("header", name),
+            AuthMethod::Hmac { header } => ("hmac", header),
+        }
+    }
+
+    /// Inverse of `kind`; header names default to X-API-Key / X-Signature
+    pub fn from_kind(kind: &str, parameter: &str) -> Self {
+        let parameter = parameter.trim();
+        let or_default = |default: &str| {
+            if parameter.is_empty() { default.to_string() } else { parameter.to_string() }
+        };
+        match kind {
+            "bearer" => AuthMethod::Bearer,
+            "basic" => AuthMethod::Basic { username: parameter.to_string() },
+            "header" => AuthMethod::Header { name: or_default("X-API-Key") },
+            "hmac" => AuthMethod::Hmac { header: or_default("X-Signature") },
+            _ => AuthMethod::None,
+        }
+    }
+}
+
+/// Add the credentials of `method` to an upload request. `body` reads the exact request
+/// body, signed for HMAC.
+pub fn authorize(
+    request: reqwest::blocking::RequestBuilder,
+    method: &AuthMethod,
+    destination: &str,
+    body: &mut dyn Read,
+) -> Result<reqwest::blocking::RequestBuilder, String> {
+    if !method.needs_secret() {
+        return Ok(request);
+    }
+    let secret = load_secret(destination)?
+        .ok_or_else(|| format!("No upload secret in the keyring for {}", destination))?;
+
+    Ok(match method {
+        AuthMethod::None => request,
+        AuthMethod::Bearer => request.bearer_auth(secret),
+        AuthMethod::Basic { username } => request.basic_auth(username, Some(secret)),
+        AuthMethod::Header { name } => request.header(name.as_str(), secret),
+        AuthMethod::Hmac { header } => {
+            let signature = to_hex(&hmac_sha256_read(secret.as_bytes(), body).map_err(|e| e.to_string())?);
+            request.header(header.as_str(), format!("sha256={}", signature))
+        }
+    })
+}
+
+/// HMAC (RFC 2104) over SHA-256
+pub fn hmac_sha256(key: &[u8], message: &[u8]) -> Vec<u8> {
+    hmac_sha256_read(key, &mut &message[..]).expect("reading a slice does not fail")
+}
+
+/// HMAC over SHA-256 of everything `message` reads
+pub fn hmac_sha256_read(key: &[u8], message: &mut dyn Read) -> std::io::Result<Vec<u8>> {
+    const BLOCK_SIZE: usize = 64;
+
+    let mut block = if key.len() > BLOCK_SIZE { sha256(&[key]) } else { key.to_vec() };
+    block.resize(BLOCK_SIZE, 0);
+    let inner_key: Vec<u8> = block.iter().map(|b| b ^ 0x36).collect();
+    let outer_key: Vec<u8> = block.iter().map(|b| b ^ 0x5c).collect();
+
+    let inner = sha256_read(&mut inner_key.as_slice().chain(message))?;
+    Ok(sha256(&[&outer_key, &inner]))
+}
+
+/// SHA-256 of the concatenated parts
+pub fn sha256(parts: &[&[u8]]) -> Vec<u8> {
+    let mut checksum = glib::Checksum::new(glib::ChecksumType::Sha256).expect("SHA-256 is supported by GLib");
+    for part in parts {
+        checksum.update(part);
+    }
+    checksum.digest()
+}
+
+/// SHA-256 of everything `reader` reads, a buffer at a time
+pub fn sha256_read(reader: &mut dyn Read) -> std::io::Result<Vec<u8>> {
+    let mut checksum = glib::Checksum::new(glib::ChecksumType::Sha256).expect("SHA-256 is supported by GLib");
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let count = reader.read(&mut buffer)?;
+        if count == 0 {
+            return Ok(checksum.digest());
+        }
+        checksum.update(&buffer[..count]);
+    }
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Secret of a destination, if one is stored
+pub fn load_secret(destination: &str) -> Result<Option<String>, String> {
+    keyring::lookup(&keyring_attributes(destination))
+}
+
+pub fn store_secret(destination: &str, secret: &str) -> Result<(), String> {
+    let label = format!("Audio Recorder upload ({})", destination);
+    keyring::store(&label, &keyring_attributes(destination), secret)
+}
+
+pub fn clear_secret(destination: &str) {
+    if let Err(e) = keyring::clear(&keyring_attributes(destination)) {
+        eprintln!("Failed to clear upload secret: {}", e);
+    }
+}
+
+/// Give each destination, as (name, URL), its own copy of the secret older versions
+/// stored for its URL, then remove the URL entries
+pub fn migrate_url_secrets(destinations: &[(&str, &str)]) -> Result<(), String> {
+    let mut migrated_urls = Vec::new();
+    for &(name, url) in destinations {
+        let Some(secret) = keyring::lookup(&url_attributes(url))? else {
+            continue;
+        };
+        if load_secret(name)?.is_none() {
+            store_secret(name, &secret)?;
+        }
+        migrated_urls.push(url);
+    }
+    for url in migrated_urls {
+        keyring::clear(&url_attributes(url))?;
+    }
+    Ok(())
+}
+
+fn keyring_attributes(destination: &str) -> [(&str, &str); 2] {
+    [("application", KEYRING_APPLICATION), ("destination", destination)]
+}
+
+/// Attributes of the secrets of older versions, shared by the destinations of a URL
+fn url_attributes(url: &str) -> [(&str, &str); 2] {
+    [("application", KEYRING_APPLICATION), ("endpoint", url)]
+}
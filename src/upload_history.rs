@@ -0,0 +1,89 @@
+// History of successful uploads, with what the destination answered. Each upload is
+// written next to the recording as `<name>.upload.json` (unless the recording is
+// deleted after upload) and added to `<data dir>/audio-recorder/upload_history.json`,
+// which keeps the last HISTORY_SIZE uploads.
+
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use crate::upload_request::UploadResponse;
+use crate::write_file_atomically;
+
+const HISTORY_SIZE: usize = 200;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UploadRecord {
+    pub file_path: String,
+    pub destination: String,
+    /// RFC 3339
+    pub uploaded_at: String,
+    #[serde(flatten)]
+    pub response: UploadResponse,
+}
+
+impl UploadRecord {
+    pub fn new(file_path: &str, destination: &str, response: UploadResponse) -> Self {
+        Self {
+            file_path: file_path.to_string(),
+            destination: destination.to_string(),
+            uploaded_at: Local::now().to_rfc3339(),
+            response,
+        }
+    }
+
+    pub fn file_name(&self) -> String {
+        Path::new(&self.file_path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.file_path.clone())
+    }
+}
+
+fn history_path() -> Option<PathBuf> {
+    Some(dirs::data_dir()?.join("audio-recorder").join("upload_history.json"))
+}
+
+fn sidecar_path(audio_path: &str) -> PathBuf {
+    Path::new(audio_path).with_extension("upload.json")
+}
+
+fn load() -> Vec<UploadRecord> {
+    history_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Store an upload in the history, and next to the recording if it is kept
+pub fn record(record: &UploadRecord, write_sidecar: bool) {
+    if write_sidecar {
+        let result = serde_json::to_string_pretty(record)
+            .map_err(std::io::Error::from)
+            .and_then(|json| write_file_atomically(&sidecar_path(&record.file_path), json.as_bytes()));
+        if let Err(e) = result {
+            eprintln!("Failed to write upload sidecar: {}", e);
+        }
+    }
+
+    let Some(path) = history_path() else {
+        return;
+    };
+    let mut history = load();
+    history.push(record.clone());
+    if history.len() > HISTORY_SIZE {
+        history.drain(..history.len() - HISTORY_SIZE);
+    }
+    let result = serde_json::to_string_pretty(&history)
+        .map_err(std::io::Error::from)
+        .and_then(|json| write_file_atomically(&path, json.as_bytes()));
+    if let Err(e) = result {
+        eprintln!("Failed to save upload history: {}", e);
+    }
+}
+
+/// Last uploads, newest first
+pub fn recent(count: usize) -> Vec<UploadRecord> {
+    load().into_iter().rev().take(count).collect()
+}
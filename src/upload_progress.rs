@@ -0,0 +1,114 @@
+// Progress and cancellation of the upload in flight. The queue worker sends one
+// recording at a time: it declares the transfer with `start`, request bodies are
+// streamed through `body` which counts the bytes read by the HTTP client, and the UI
+// is told of the changes (at most every NOTIFY_INTERVAL while sending) to read
+// `current`. `cancel` makes the body fail on its next read, which aborts the request.
+
+use std::io::Read;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::{send_ui_message, UiMessage};
+
+// Request timeout: a fixed part for the connection and the server's processing, plus
+// the time to send the body at a slow uplink rate (1 Mbit/s)
+const BASE_TIMEOUT: Duration = Duration::from_secs(60);
+const MIN_THROUGHPUT: u64 = 128 * 1024;
+const NOTIFY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Upload in flight, as shown in the window and the tray
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transfer {
+    /// File and destination
+    pub label: String,
+    pub sent: u64,
+    pub total: u64,
+}
+
+impl Transfer {
+    pub fn fraction(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        (self.sent as f64 / self.total as f64).min(1.0)
+    }
+
+    pub fn percent(&self) -> u8 {
+        (self.fraction() * 100.0) as u8
+    }
+}
+
+static TRANSFER: Mutex<Option<(String, u64)>> = Mutex::new(None);
+static SENT: AtomicU64 = AtomicU64::new(0);
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+// Last time the UI was told, None without a transfer to report
+static NOTIFIED: Mutex<Option<Instant>> = Mutex::new(None);
+
+pub fn start(label: String, total: u64) {
+    SENT.store(0, Ordering::SeqCst);
+    CANCELLED.store(false, Ordering::SeqCst);
+    *TRANSFER.lock().unwrap() = Some((label, total));
+    *NOTIFIED.lock().unwrap() = Some(Instant::now());
+    send_ui_message(UiMessage::UploadProgressChanged);
+}
+
+pub fn finish() {
+    *TRANSFER.lock().unwrap() = None;
+    *NOTIFIED.lock().unwrap() = None;
+    send_ui_message(UiMessage::UploadProgressChanged);
+}
+
+/// Tell the UI about the bytes sent, unless it was told recently
+fn notify_progress() {
+    let mut notified = NOTIFIED.lock().unwrap();
+    match *notified {
+        Some(at) if at.elapsed() >= NOTIFY_INTERVAL => {
+            *notified = Some(Instant::now());
+            send_ui_message(UiMessage::UploadProgressChanged);
+        }
+        _ => {}
+    }
+}
+
+/// Abort the upload in flight, if any
+pub fn cancel() {
+    if TRANSFER.lock().unwrap().is_some() {
+        CANCELLED.store(true, Ordering::SeqCst);
+    }
+}
+
+pub fn current() -> Option<Transfer> {
+    let transfer = TRANSFER.lock().unwrap();
+    let (label, total) = transfer.as_ref()?;
+    Some(Transfer {
+        label: label.clone(),
+        sent: SENT.load(Ordering::SeqCst),
+        total: *total,
+    })
+}
+
+/// Timeout of a request sending `size` bytes
+pub fn timeout_for(size: u64) -> Duration {
+    BASE_TIMEOUT + Duration::from_secs(size / MIN_THROUGHPUT)
+}
+
+/// Request body of `len` bytes streamed from `reader` (usually the recording file),
+/// counted in the progress of the current transfer
+pub fn body(reader: impl Read + Send + 'static, len: u64) -> reqwest::blocking::Body {
+    reqwest::blocking::Body::sized(ProgressReader(reader), len)
+}
+
+struct ProgressReader<R>(R);
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if CANCELLED.load(Ordering::SeqCst) {
+            return Err(std::io::Error::other("upload cancelled"));
+        }
+        let count = self.0.read(buf)?;
+        SENT.fetch_add(count as u64, Ordering::SeqCst);
+        notify_progress();
+        Ok(count)
+    }
+}
@@ -0,0 +1,299 @@
+// Durable upload queue. Uploads requested from the upload dialog are stored in
+// `<data dir>/audio-recorder/upload_queue.json` and sent by a single worker thread.
+// A failed upload is retried with exponential backoff, across restarts; a job only
+// leaves the queue after a 2xx response, or when removed from the UI.
+
+use std::path::PathBuf;
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::upload_history::{self, UploadRecord};
+use crate::upload_request::UploadResponse;
+use crate::{
+    markers, send_ui_message, show_notification, show_notification_with_link, upload_progress, upload_recording,
+    write_file_atomically, BackgroundTask, Config, UiMessage, UploadDestination,
+};
+
+// Delay before the first retry, doubled after each failure up to RETRY_MAX_DELAY
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(30);
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(3600);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UploadJob {
+    pub id: u64,
+    pub file_path: String,
+    /// Destination name; its current URL and auth are used on each attempt
+    #[serde(default)]
+    pub destination: Option<String>,
+    /// URL at enqueue time, used if the destination has been removed since
+    pub endpoint: String,
+    pub min_speakers: u32,
+    pub max_speakers: u32,
+    /// Delete the local file (and its marker sidecars) once uploaded
+    pub delete_after_upload: bool,
+    pub attempts: u32,
+    /// Unix time of the next attempt
+    pub next_attempt: i64,
+    pub last_error: Option<String>,
+    /// Being sent right now (not persisted)
+    #[serde(skip)]
+    pub uploading: bool,
+}
+
+impl UploadJob {
+    /// Destination name, or the URL for jobs queued before destinations existed
+    pub fn destination_label(&self) -> &str {
+        self.destination.as_deref().unwrap_or(&self.endpoint)
+    }
+
+    pub fn file_name(&self) -> String {
+        std::path::Path::new(&self.file_path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.file_path.clone())
+    }
+
+    /// Destination to send to, as currently configured
+    fn resolve_destination(&self) -> Result<UploadDestination, String> {
+        let Some(name) = &self.destination else {
+            return Ok(UploadDestination {
+                name: self.endpoint.clone(),
+                url: self.endpoint.clone(),
+                ..Default::default()
+            });
+        };
+        Config::load()
+            .and_then(|config| {
+                config
+                    .upload_destinations
+                    .into_iter()
+                    .find(|destination| &destination.name == name)
+            })
+            .ok_or_else(|| format!("destination {} is no longer configured", name))
+    }
+}
+
+struct Queue {
+    jobs: Vec<UploadJob>,
+    next_id: u64,
+}
+
+static QUEUE: Mutex<Queue> = Mutex::new(Queue { jobs: Vec::new(), next_id: 1 });
+// Wakes the worker when a job is added or retried
+static WAKE: Condvar = Condvar::new();
+
+fn queue_path() -> Option<PathBuf> {
+    Some(dirs::data_dir()?.join("audio-recorder").join("upload_queue.json"))
+}
+
+fn now() -> i64 {
+    Utc::now().timestamp()
+}
+
+/// Load the saved queue and start the worker thread
+pub fn start() {
+    let saved: Vec<UploadJob> = queue_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+    if !saved.is_empty() {
+        println!("Upload queue: {} pending upload(s)", saved.len());
+    }
+    {
+        let mut queue = QUEUE.lock().unwrap();
+        queue.next_id = saved.iter().map(|job| job.id + 1).max().unwrap_or(1);
+        queue.jobs = saved;
+    }
+    send_ui_message(UiMessage::UploadQueueChanged);
+
+    std::thread::spawn(run_worker);
+}
+
+fn save(queue: &Queue) {
+    let Some(path) = queue_path() else {
+        eprintln!("Upload queue not saved: no data directory");
+        return;
+    };
+    let result = serde_json::to_string_pretty(&queue.jobs)
+        .map_err(std::io::Error::from)
+        .and_then(|json| write_file_atomically(&path, json.as_bytes()));
+    if let Err(e) = result {
+        eprintln!("Failed to save upload queue: {}", e);
+    }
+}
+
+/// Save the queue, tell the UI and wake the worker
+fn changed(queue: &Queue) {
+    save(queue);
+    send_ui_message(UiMessage::UploadQueueChanged);
+    WAKE.notify_all();
+}
+
+pub fn enqueue(
+    file_path: &str,
+    destination: &UploadDestination,
+    min_speakers: u32,
+    max_speakers: u32,
+    delete_after_upload: bool,
+) {
+    let mut queue = QUEUE.lock().unwrap();
+    let id = queue.next_id;
+    queue.next_id += 1;
+    queue.jobs.push(UploadJob {
+        id,
+        file_path: file_path.to_string(),
+        destination: Some(destination.name.clone()),
+        endpoint: destination.url.clone(),
+        min_speakers,
+        max_speakers,
+        delete_after_upload,
+        attempts: 0,
+        next_attempt: now(),
+        last_error: None,
+        uploading: false,
+    });
+    println!("Upload queued: {} -> {}", file_path, destination.name);
+    changed(&queue);
+}
+
+/// Current jobs, oldest first
+pub fn jobs() -> Vec<UploadJob> {
+    QUEUE.lock().unwrap().jobs.clone()
+}
+
+/// Try a job again now instead of waiting for its backoff
+pub fn retry_now(id: u64) {
+    let mut queue = QUEUE.lock().unwrap();
+    if let Some(job) = queue.jobs.iter_mut().find(|job| job.id == id) {
+        job.next_attempt = now();
+        changed(&queue);
+    }
+}
+
+/// Drop a job from the queue, aborting it if it is being sent; its local file is kept
+pub fn remove(id: u64) {
+    let mut queue = QUEUE.lock().unwrap();
+    if queue.jobs.iter().any(|job| job.id == id && job.uploading) {
+        upload_progress::cancel();
+    }
+    queue.jobs.retain(|job| job.id != id);
+    changed(&queue);
+}
+
+/// Abort the upload in flight and drop it from the queue
+pub fn cancel_upload() {
+    let uploading = QUEUE.lock().unwrap().jobs.iter().find(|job| job.uploading).cloned();
+    if let Some(job) = uploading {
+        println!("Upload cancelled: {}", job.file_path);
+        remove(job.id);
+        show_notification("Upload annulé", &format!("{} est conservé localement", job.file_name()));
+    }
+}
+
+fn retry_delay(attempts: u32) -> Duration {
+    let exponent = attempts.saturating_sub(1).min(16);
+    (RETRY_BASE_DELAY * 2u32.pow(exponent)).min(RETRY_MAX_DELAY)
+}
+
+fn run_worker() {
+    let mut queue = QUEUE.lock().unwrap();
+    loop {
+        let now = now();
+        let Some(job) = queue
+            .jobs
+            .iter_mut()
+            .filter(|job| job.next_attempt <= now)
+            .min_by_key(|job| job.next_attempt)
+        else {
+            // Sleep until the next job is due, or until woken by a change
+            let wait = queue
+                .jobs
+                .iter()
+                .map(|job| Duration::from_secs((job.next_attempt - now).max(1) as u64))
+                .min()
+                .unwrap_or(RETRY_MAX_DELAY);
+            queue = WAKE.wait_timeout(queue, wait).unwrap().0;
+            continue;
+        };
+
+        job.uploading = true;
+        let job = job.clone();
+        send_ui_message(UiMessage::UploadQueueChanged);
+        let task = BackgroundTask::start();
+        drop(queue);
+
+        let result = job.resolve_destination().and_then(|destination| {
+            let size = std::fs::metadata(&job.file_path).map(|m| m.len()).unwrap_or(0);
+            upload_progress::start(format!("{} → {}", job.file_name(), job.destination_label()), size);
+            let result = upload_recording(&job.file_path, &destination, job.min_speakers, job.max_speakers);
+            upload_progress::finish();
+            result.map_err(|e| e.to_string())
+        });
+        drop(task);
+
+        queue = QUEUE.lock().unwrap();
+        finish(&mut queue, &job, result);
+    }
+}
+
+fn finish(queue: &mut Queue, job: &UploadJob, result: Result<UploadResponse, String>) {
+    let Some(index) = queue.jobs.iter().position(|queued| queued.id == job.id) else {
+        // Removed from the UI while uploading
+        return;
+    };
+
+    match result {
+        Ok(response) => {
+            println!("Upload succeeded: {}", job.file_path);
+            let mut message = format!("{} a été envoyé ({})", job.file_name(), job.destination_label());
+            if let Some(id) = &response.id {
+                message.push_str(&format!("\nIdentifiant : {}", id));
+            }
+            match &response.url {
+                Some(url) => show_notification_with_link("Upload réussi", &message, url),
+                None => show_notification("Upload réussi", &message),
+            }
+            upload_history::record(
+                &UploadRecord::new(&job.file_path, job.destination_label(), response),
+                !job.delete_after_upload,
+            );
+            queue.jobs.remove(index);
+
+            if job.delete_after_upload {
+                if let Err(e) = std::fs::remove_file(&job.file_path) {
+                    eprintln!("Failed to delete local file: {}", e);
+                } else {
+                    println!("Local file deleted (save_locally=false)");
+                }
+                markers::remove_sidecars(&job.file_path);
+            }
+            crate::refresh_tray_recordings();
+        }
+        Err(e) => {
+            let queued = &mut queue.jobs[index];
+            queued.uploading = false;
+            queued.attempts += 1;
+            let delay = retry_delay(queued.attempts);
+            queued.next_attempt = now() + delay.as_secs() as i64;
+            queued.last_error = Some(e.clone());
+            eprintln!(
+                "Upload failed (attempt {}), retrying in {}s: {}",
+                queued.attempts,
+                delay.as_secs(),
+                e
+            );
+            // Only the first failure is notified: retries are visible in the queue
+            if queued.attempts == 1 {
+                show_notification(
+                    "Échec de l'upload",
+                    &format!("Erreur: {}\nNouvel essai automatique dans {} s", e, delay.as_secs()),
+                );
+            }
+        }
+    }
+    save(queue);
+    send_ui_message(UiMessage::UploadQueueChanged);
+}
@@ -0,0 +1,877 @@
+// Shape of the upload HTTP request, set per destination: method, body mode (multipart
+// form, raw file body or JSON with the file base64-encoded), field names and static
+// headers. The URL, header values and extra fields are templates, expanded with
+// {filename}, {stem}, {timestamp}, {date}, {time}, {min_speakers}, {max_speakers},
+// {size} and {destination}; `{{` and `}}` stand for literal braces. The response
+// mapping picks a link and a job id out of a JSON response.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+
+use base64::Engine;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const PLACEHOLDERS: [&str; 9] = [
+    "filename",
+    "stem",
+    "timestamp",
+    "date",
+    "time",
+    "min_speakers",
+    "max_speakers",
+    "size",
+    "destination",
+];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum HttpMethod {
+    #[default]
+    Post,
+    Put,
+    Patch,
+}
+
+impl HttpMethod {
+    pub const ALL: [HttpMethod; 3] = [HttpMethod::Post, HttpMethod::Put, HttpMethod::Patch];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HttpMethod::Post => "POST",
+            HttpMethod::Put => "PUT",
+            HttpMethod::Patch => "PATCH",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BodyMode {
+    /// multipart/form-data with the file part and one text part per field
+    #[default]
+    Multipart,
+    /// The audio file is the whole body; metadata can only go in the URL or headers
+    Raw,
+    /// application/json object with the file base64-encoded
+    Base64Json,
+}
+
+impl BodyMode {
+    pub const ALL: [BodyMode; 3] = [BodyMode::Multipart, BodyMode::Raw, BodyMode::Base64Json];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            BodyMode::Multipart => "multipart",
+            BodyMode::Raw => "raw",
+            BodyMode::Base64Json => "base64_json",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RequestShape {
+    pub method: HttpMethod,
+    pub body: BodyMode,
+    /// Field names of the file and metadata; an empty name leaves the value out
+    pub file_field: String,
+    pub filename_field: String,
+    pub timestamp_field: String,
+    pub min_speakers_field: String,
+    pub max_speakers_field: String,
+    pub markers_field: String,
+    /// When set, the metadata and extra fields are grouped in one JSON object under
+    /// this name (a single JSON part in multipart mode)
+    pub metadata_json_field: String,
+    /// Static headers; values are templates
+    pub headers: BTreeMap<String, String>,
+    pub response: ResponseMapping,
+}
+
+/// Where to find values in a JSON response: JSON pointers (`/data/url`) or dotted
+/// paths (`data.url`); empty to ignore
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResponseMapping {
+    /// Link to open from the notification (e.g. a transcript page)
+    pub url: String,
+    /// Job or document id
+    pub id: String,
+}
+
+/// What a destination answered to an upload
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct UploadResponse {
+    /// Where the recording was stored or sent
+    pub location: String,
+    pub url: Option<String>,
+    pub id: Option<String>,
+    /// Response body: JSON as is, anything else as a string
+    pub body: Option<Value>,
+}
+
+impl ResponseMapping {
+    /// Response of a webhook, with the mapped values picked out of a JSON body
+    pub fn parse(&self, location: &str, body: &str) -> UploadResponse {
+        let json = serde_json::from_str::<Value>(body).ok();
+        let lookup = |path: &str| -> Option<String> {
+            if path.is_empty() {
+                return None;
+            }
+            let pointer = if path.starts_with('/') { path.to_string() } else { format!("/{}", path.replace('.', "/")) };
+            match json.as_ref()?.pointer(&pointer)? {
+                Value::String(text) => Some(text.clone()),
+                Value::Null => None,
+                other => Some(other.to_string()),
+            }
+        };
+        UploadResponse {
+            location: location.to_string(),
+            url: lookup(&self.url).filter(|url| url.starts_with("http://") || url.starts_with("https://")),
+            id: lookup(&self.id),
+            body: json.or_else(|| (!body.trim().is_empty()).then(|| Value::from(body.trim()))),
+        }
+    }
+}
+
+impl Default for RequestShape {
+    fn default() -> Self {
+        // The request sent to N8N webhooks before the shape was configurable
+        Self {
+            method: HttpMethod::Post,
+            body: BodyMode::Multipart,
+            file_field: "file".to_string(),
+            filename_field: "filename".to_string(),
+            timestamp_field: "timestamp".to_string(),
+            min_speakers_field: "min_speakers".to_string(),
+            max_speakers_field: "max_speakers".to_string(),
+            markers_field: "markers".to_string(),
+            metadata_json_field: String::new(),
+            headers: BTreeMap::new(),
+            response: ResponseMapping::default(),
+        }
+    }
+}
+
+/// What is known about the recording being uploaded
+pub struct UploadMetadata<'a> {
+    pub file_name: &'a str,
+    pub timestamp: DateTime<Local>,
+    pub min_speakers: u32,
+    pub max_speakers: u32,
+    /// JSON array of the markers sidecar
+    pub markers_json: Option<&'a str>,
+    pub destination: &'a str,
+    pub size: usize,
+}
+
+impl UploadMetadata<'_> {
+    /// Values of the template placeholders
+    pub fn variables(&self) -> Vec<(&'static str, String)> {
+        let stem = self.file_name.rsplit_once('.').map_or(self.file_name, |(stem, _)| stem);
+        vec![
+            ("filename", self.file_name.to_string()),
+            ("stem", stem.to_string()),
+            ("timestamp", self.timestamp.to_rfc3339()),
+            ("date", self.timestamp.format("%Y-%m-%d").to_string()),
+            ("time", self.timestamp.format("%H:%M:%S").to_string()),
+            ("min_speakers", self.min_speakers.to_string()),
+            ("max_speakers", self.max_speakers.to_string()),
+            ("size", self.size.to_string()),
+            ("destination", self.destination.to_string()),
+        ]
+    }
+}
+
+/// Request ready to send; `headers` include the content type
+pub struct PreparedRequest {
+    pub method: HttpMethod,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: RequestBody,
+}
+
+// Stands for the file in the JSON body, which is split around it
+const FILE_MARKER: &str = "\0audio-recorder-file\0";
+// Bytes of the file encoded at a time in a base64 body, whole 3-byte groups
+const BASE64_CHUNK: usize = 48 * 1024;
+
+/// Body of a prepared request: the recording between a prefix and a suffix (the rest
+/// of the multipart form or JSON object), read from disk while the request is sent
+pub struct RequestBody {
+    prefix: Vec<u8>,
+    file: PathBuf,
+    file_len: u64,
+    /// The file is sent base64-encoded, inside a JSON string
+    base64: bool,
+    suffix: Vec<u8>,
+}
+
+impl RequestBody {
+    pub fn content_length(&self) -> u64 {
+        let file_len = if self.base64 { self.file_len.div_ceil(3) * 4 } else { self.file_len };
+        self.prefix.len() as u64 + file_len + self.suffix.len() as u64
+    }
+
+    /// The whole body, from a new handle on the file: signing it and each attempt at
+    /// sending it read it again
+    pub fn reader(&self) -> std::io::Result<impl Read + Send + 'static> {
+        let file = File::open(&self.file)?.take(self.file_len);
+        let content: Box<dyn Read + Send> = if self.base64 {
+            Box::new(Base64Reader {
+                inner: file,
+                encoded: Vec::new(),
+                position: 0,
+            })
+        } else {
+            Box::new(file)
+        };
+        Ok(Cursor::new(self.prefix.clone())
+            .chain(content)
+            .chain(Cursor::new(self.suffix.clone())))
+    }
+}
+
+/// Standard base64 encoding of what `inner` reads
+struct Base64Reader<R> {
+    inner: R,
+    encoded: Vec<u8>,
+    position: usize,
+}
+
+impl<R: Read> Read for Base64Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.position == self.encoded.len() {
+            // Only the last chunk can be short, so padding only ends the output
+            let mut chunk = Vec::with_capacity(BASE64_CHUNK);
+            (&mut self.inner).take(BASE64_CHUNK as u64).read_to_end(&mut chunk)?;
+            self.encoded = base64::engine::general_purpose::STANDARD.encode(&chunk).into_bytes();
+            self.position = 0;
+        }
+        let count = buf.len().min(self.encoded.len() - self.position);
+        buf[..count].copy_from_slice(&self.encoded[self.position..self.position + count]);
+        self.position += count;
+        Ok(count)
+    }
+}
+
+/// Expand `{placeholder}`s. Unknown placeholders and unbalanced braces are errors.
+pub fn expand(template: &str, variables: &[(&str, String)]) -> Result<String, String> {
+    let mut output = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                output.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                output.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err(format!("unterminated placeholder {{{} in \"{}\"", name, template)),
+                    }
+                }
+                let value = variables
+                    .iter()
+                    .find(|(variable, _)| *variable == name)
+                    .map(|(_, value)| value)
+                    .ok_or_else(|| format!("unknown placeholder {{{}}} in \"{}\"", name, template))?;
+                output.push_str(value);
+            }
+            '}' => return Err(format!("unmatched '}}' in \"{}\"", template)),
+            c => output.push(c),
+        }
+    }
+    Ok(output)
+}
+
+/// Placeholder values used to check templates before any recording exists
+fn sample_variables() -> Vec<(&'static str, String)> {
+    UploadMetadata {
+        file_name: "recording.ogg",
+        timestamp: Local::now(),
+        min_speakers: 1,
+        max_speakers: 2,
+        markers_json: None,
+        destination: "destination",
+        size: 0,
+    }
+    .variables()
+}
+
+/// Check that a template only uses known placeholders
+pub fn check_template(template: &str) -> Result<(), String> {
+    expand(template, &sample_variables()).map(|_| ())
+}
+
+/// RFC 3986 percent-encoding of all but unreserved characters (and `/` in keys)
+pub fn uri_encode(value: &str, encode_slash: bool) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => encoded.push(byte as char),
+            b'/' if !encode_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn check_unique(names: &[&str]) -> Result<(), String> {
+    for (i, name) in names.iter().enumerate() {
+        if names[..i].contains(name) {
+            return Err(format!("field \"{}\" is used twice", name));
+        }
+    }
+    Ok(())
+}
+
+fn is_header_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+impl RequestShape {
+    /// Check the shape and templates of a destination before saving it
+    pub fn validate(&self, url: &str, extra_fields: &BTreeMap<String, String>) -> Result<(), String> {
+        let sample = sample_variables();
+
+        let url = expand(url, &sample)?;
+        if !(url.starts_with("http://") || url.starts_with("https://")) {
+            return Err(format!("URL must start with http:// or https:// ({})", url));
+        }
+        for (name, value) in &self.headers {
+            if !is_header_name(name) {
+                return Err(format!("invalid header name \"{}\"", name));
+            }
+            if expand(value, &sample)?.contains(['\r', '\n']) {
+                return Err(format!("header {} contains a line break", name));
+            }
+        }
+        for value in extra_fields.values() {
+            expand(value, &sample)?;
+        }
+
+        if self.body == BodyMode::Raw {
+            if !extra_fields.is_empty() {
+                return Err("extra fields cannot be sent with a raw body: use URL or header templates".to_string());
+            }
+            return Ok(());
+        }
+
+        if self.file_field.trim().is_empty() {
+            return Err("the file field name is required".to_string());
+        }
+        // The metadata names share the form (or JSON object) with the file field,
+        // unless they are grouped under metadata_json_field
+        let metadata_names: Vec<&str> = self
+            .metadata_names()
+            .into_iter()
+            .chain(extra_fields.keys().map(String::as_str))
+            .collect();
+        if self.metadata_json_field.is_empty() {
+            check_unique(&[&[self.file_field.as_str()][..], &metadata_names].concat())?;
+        } else {
+            check_unique(&[self.file_field.as_str(), self.metadata_json_field.as_str()])?;
+            check_unique(&metadata_names)?;
+        }
+        Ok(())
+    }
+
+    fn metadata_names(&self) -> Vec<&str> {
+        [
+            &self.filename_field,
+            &self.timestamp_field,
+            &self.min_speakers_field,
+            &self.max_speakers_field,
+            &self.markers_field,
+        ]
+        .into_iter()
+        .map(String::as_str)
+        .filter(|name| !name.is_empty())
+        .collect()
+    }
+
+    /// Metadata and extra fields in order, as JSON values
+    fn metadata_values(
+        &self,
+        extra_fields: &BTreeMap<String, String>,
+        metadata: &UploadMetadata,
+        variables: &[(&str, String)],
+    ) -> Result<Vec<(String, Value)>, String> {
+        let markers = metadata
+            .markers_json
+            .and_then(|json| serde_json::from_str::<Value>(json).ok());
+        let mut values = vec![
+            (&self.filename_field, Some(Value::from(metadata.file_name))),
+            (&self.timestamp_field, Some(Value::from(metadata.timestamp.to_rfc3339()))),
+            (&self.min_speakers_field, Some(Value::from(metadata.min_speakers))),
+            (&self.max_speakers_field, Some(Value::from(metadata.max_speakers))),
+            (&self.markers_field, markers),
+        ]
+        .into_iter()
+        .filter(|(name, _)| !name.is_empty())
+        .filter_map(|(name, value)| Some((name.clone(), value?)))
+        .collect::<Vec<_>>();
+        for (name, template) in extra_fields {
+            values.push((name.clone(), Value::from(expand(template, variables)?)));
+        }
+        Ok(values)
+    }
+
+    /// Build the request for a recording; the file is only read when it is sent
+    pub fn prepare(
+        &self,
+        url: &str,
+        extra_fields: &BTreeMap<String, String>,
+        metadata: &UploadMetadata,
+        file: &Path,
+    ) -> Result<PreparedRequest, String> {
+        let variables = metadata.variables();
+        let mut headers = Vec::new();
+        for (name, value) in &self.headers {
+            headers.push((name.clone(), expand(value, &variables)?));
+        }
+
+        let (content_type, prefix, suffix) = match self.body {
+            BodyMode::Raw => ("audio/ogg".to_string(), Vec::new(), Vec::new()),
+            BodyMode::Multipart => {
+                let values = self.metadata_values(extra_fields, metadata, &variables)?;
+                let mut parts: Vec<(String, Option<&str>, String)> = Vec::new();
+                if self.metadata_json_field.is_empty() {
+                    for (name, value) in values {
+                        let text = match value {
+                            Value::String(text) => text,
+                            other => other.to_string(),
+                        };
+                        parts.push((name, None, text));
+                    }
+                } else {
+                    let object: serde_json::Map<String, Value> = values.into_iter().collect();
+                    parts.push((
+                        self.metadata_json_field.clone(),
+                        Some("application/json"),
+                        Value::Object(object).to_string(),
+                    ));
+                }
+                multipart_body(&parts, (&self.file_field, metadata.file_name, "audio/ogg"))
+            }
+            BodyMode::Base64Json => {
+                let values = self.metadata_values(extra_fields, metadata, &variables)?;
+                let mut object = serde_json::Map::new();
+                object.insert(self.file_field.clone(), Value::from(FILE_MARKER));
+                if self.metadata_json_field.is_empty() {
+                    object.extend(values);
+                } else {
+                    object.insert(
+                        self.metadata_json_field.clone(),
+                        Value::Object(values.into_iter().collect()),
+                    );
+                }
+                let json = Value::Object(object).to_string();
+                let marker = Value::from(FILE_MARKER).to_string();
+                let (prefix, suffix) = match json.split_once(&marker) {
+                    Some((prefix, suffix)) if !suffix.contains(&marker) => (prefix, suffix),
+                    _ => return Err("a field value clashes with the file placeholder".to_string()),
+                };
+                (
+                    "application/json".to_string(),
+                    format!("{}\"", prefix).into_bytes(),
+                    format!("\"{}", suffix).into_bytes(),
+                )
+            }
+        };
+        // A Content-Type set in the headers wins (e.g. audio/x-opus for a raw body)
+        if !headers.iter().any(|(name, _)| name.eq_ignore_ascii_case("content-type")) {
+            headers.push(("Content-Type".to_string(), content_type));
+        }
+
+        let file_len = std::fs::metadata(file)
+            .map_err(|e| format!("cannot read {}: {}", file.display(), e))?
+            .len();
+        Ok(PreparedRequest {
+            method: self.method,
+            url: expand(url, &variables)?,
+            headers,
+            body: RequestBody {
+                prefix,
+                file: file.to_path_buf(),
+                file_len,
+                base64: self.body == BodyMode::Base64Json,
+                suffix,
+            },
+        })
+    }
+}
+
+/// multipart/form-data body with text parts (optionally typed) and one file part.
+/// Returns the content type (with its boundary) and the bytes before and after the
+/// file content.
+fn multipart_body(
+    parts: &[(String, Option<&str>, String)],
+    (file_field, file_name, mime): (&str, &str, &str),
+) -> (String, Vec<u8>, Vec<u8>) {
+    let boundary = format!("audio-recorder-{:x}", Local::now().timestamp_nanos_opt().unwrap_or_default());
+    let quote = |value: &str| value.replace('"', "%22").replace(['\r', '\n'], " ");
+
+    let mut prefix = Vec::with_capacity(1024);
+    for (name, part_type, value) in parts {
+        prefix.extend_from_slice(format!("--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n", boundary, quote(name)).as_bytes());
+        if let Some(part_type) = part_type {
+            prefix.extend_from_slice(format!("Content-Type: {}\r\n", part_type).as_bytes());
+        }
+        prefix.extend_from_slice(format!("\r\n{}\r\n", value).as_bytes());
+    }
+    prefix.extend_from_slice(
+        format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n",
+            boundary,
+            quote(file_field),
+            quote(file_name),
+            mime
+        )
+        .as_bytes(),
+    );
+    let suffix = format!("\r\n--{}--\r\n", boundary).into_bytes();
+
+    (format!("multipart/form-data; boundary={}", boundary), prefix, suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    fn variables() -> Vec<(&'static str, String)> {
+        vec![("filename", "talk.ogg".to_string()), ("size", "42".to_string())]
+    }
+
+    fn metadata(size: usize) -> UploadMetadata<'static> {
+        UploadMetadata {
+            file_name: "talk.ogg",
+            timestamp: Local::now(),
+            min_speakers: 2,
+            max_speakers: 3,
+            markers_json: Some("[{\"time\":1.5,\"label\":\"intro\"}]"),
+            destination: "Transcription",
+            size,
+        }
+    }
+
+    /// Recording of `len` bytes in the temporary directory, named after the test
+    fn recording(name: &str, len: usize) -> (PathBuf, Vec<u8>) {
+        let content: Vec<u8> = (0..len).map(|i| (i * 31 % 256) as u8).collect();
+        let path = std::env::temp_dir().join(format!("audio-recorder-test-{}-{}.ogg", name, std::process::id()));
+        std::fs::write(&path, &content).unwrap();
+        (path, content)
+    }
+
+    fn read_body(prepared: &PreparedRequest) -> Vec<u8> {
+        let mut body = Vec::new();
+        prepared.body.reader().unwrap().read_to_end(&mut body).unwrap();
+        assert_eq!(body.len() as u64, prepared.body.content_length());
+        body
+    }
+
+    fn header<'a>(prepared: &'a PreparedRequest, name: &str) -> Option<&'a str> {
+        prepared
+            .headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn boundary(content_type: &str) -> &str {
+        content_type
+            .strip_prefix("multipart/form-data; boundary=")
+            .expect("multipart content type")
+    }
+
+    #[test]
+    fn expand_doubled_braces_are_literal() {
+        assert_eq!(expand("{{{filename}}} {{size}}", &variables()).unwrap(), "{talk.ogg} {size}");
+    }
+
+    #[test]
+    fn expand_rejects_unknown_placeholder() {
+        let error = expand("/upload/{user}", &variables()).unwrap_err();
+        assert!(error.contains("unknown placeholder {user}"), "{}", error);
+    }
+
+    #[test]
+    fn expand_rejects_unmatched_closing_brace() {
+        let error = expand("/upload/size}", &variables()).unwrap_err();
+        assert!(error.contains("unmatched '}'"), "{}", error);
+    }
+
+    #[test]
+    fn expand_rejects_unterminated_placeholder() {
+        // A known name must not hide the missing brace, nor swallow the rest
+        let error = expand("/upload/{filename", &variables()).unwrap_err();
+        assert!(error.contains("unterminated placeholder {filename"), "{}", error);
+        let error = expand("{size", &variables()).unwrap_err();
+        assert!(error.contains("unterminated"), "{}", error);
+    }
+
+    #[test]
+    fn validate_rejects_duplicate_fields() {
+        let shape = RequestShape::default();
+        let extra = BTreeMap::from([("filename".to_string(), "x".to_string())]);
+        let error = shape.validate("https://example.com/hook", &extra).unwrap_err();
+        assert!(error.contains("\"filename\" is used twice"), "{}", error);
+
+        // Grouped under a JSON field, metadata names no longer clash with the file field
+        let shape = RequestShape {
+            file_field: "filename".to_string(),
+            metadata_json_field: "metadata".to_string(),
+            ..Default::default()
+        };
+        assert!(shape.validate("https://example.com/hook", &BTreeMap::new()).is_ok());
+        let shape = RequestShape {
+            metadata_json_field: "file".to_string(),
+            ..Default::default()
+        };
+        assert!(shape.validate("https://example.com/hook", &BTreeMap::new()).is_err());
+    }
+
+    #[test]
+    fn validate_rejects_extra_fields_with_raw_body() {
+        let shape = RequestShape {
+            body: BodyMode::Raw,
+            ..Default::default()
+        };
+        assert!(shape.validate("https://example.com/{filename}", &BTreeMap::new()).is_ok());
+        let extra = BTreeMap::from([("source".to_string(), "desktop".to_string())]);
+        let error = shape.validate("https://example.com/{filename}", &extra).unwrap_err();
+        assert!(error.contains("raw body"), "{}", error);
+    }
+
+    #[test]
+    fn validate_rejects_line_breaks_in_headers() {
+        for value in ["a\r\nX-Injected: 1", "a\nb", "a\rb"] {
+            let shape = RequestShape {
+                headers: BTreeMap::from([("X-Source".to_string(), value.to_string())]),
+                ..Default::default()
+            };
+            let error = shape.validate("https://example.com/hook", &BTreeMap::new()).unwrap_err();
+            assert!(error.contains("line break"), "{}", error);
+        }
+        let shape = RequestShape {
+            headers: BTreeMap::from([("X Source".to_string(), "a".to_string())]),
+            ..Default::default()
+        };
+        assert!(shape.validate("https://example.com/hook", &BTreeMap::new()).is_err());
+    }
+
+    #[test]
+    fn prepare_raw_body_is_the_file() {
+        let (path, content) = recording("raw", 1000);
+        let shape = RequestShape {
+            method: HttpMethod::Put,
+            body: BodyMode::Raw,
+            headers: BTreeMap::from([("X-Name".to_string(), "{stem}".to_string())]),
+            ..Default::default()
+        };
+        let prepared = shape
+            .prepare("https://example.com/files/{filename}", &BTreeMap::new(), &metadata(content.len()), &path)
+            .unwrap();
+        assert_eq!(prepared.method, HttpMethod::Put);
+        assert_eq!(prepared.url, "https://example.com/files/talk.ogg");
+        assert_eq!(header(&prepared, "Content-Type"), Some("audio/ogg"));
+        assert_eq!(header(&prepared, "X-Name"), Some("talk"));
+        assert_eq!(read_body(&prepared), content);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn prepare_content_type_header_wins() {
+        let (path, content) = recording("content-type", 10);
+        let shape = RequestShape {
+            body: BodyMode::Raw,
+            headers: BTreeMap::from([("content-type".to_string(), "audio/x-opus".to_string())]),
+            ..Default::default()
+        };
+        let prepared = shape
+            .prepare("https://example.com/", &BTreeMap::new(), &metadata(content.len()), &path)
+            .unwrap();
+        assert_eq!(prepared.headers, vec![("content-type".to_string(), "audio/x-opus".to_string())]);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn prepare_multipart_frames_the_file() {
+        let (path, content) = recording("multipart", 5000);
+        let extra = BTreeMap::from([("source".to_string(), "{destination}".to_string())]);
+        let prepared = RequestShape::default()
+            .prepare("https://example.com/hook", &extra, &metadata(content.len()), &path)
+            .unwrap();
+        let content_type = header(&prepared, "Content-Type").unwrap();
+        let boundary = boundary(content_type);
+        let body = read_body(&prepared);
+
+        let text = String::from_utf8_lossy(&body);
+        assert!(text.starts_with(&format!("--{}\r\n", boundary)));
+        assert!(text.ends_with(&format!("\r\n--{}--\r\n", boundary)));
+        for (name, value) in [("filename", "talk.ogg"), ("min_speakers", "2"), ("max_speakers", "3"), ("source", "Transcription")] {
+            assert!(
+                text.contains(&format!("Content-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n", name, value)),
+                "missing field {}",
+                name
+            );
+        }
+        let file_header = format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"talk.ogg\"\r\nContent-Type: audio/ogg\r\n\r\n",
+            boundary
+        );
+        let start = body
+            .windows(file_header.len())
+            .position(|window| window == file_header.as_bytes())
+            .expect("file part")
+            + file_header.len();
+        assert_eq!(&body[start..start + content.len()], &content[..]);
+        assert_eq!(body.len(), start + content.len() + format!("\r\n--{}--\r\n", boundary).len());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn prepare_multipart_groups_metadata_as_json() {
+        let (path, content) = recording("multipart-json", 10);
+        let shape = RequestShape {
+            metadata_json_field: "metadata".to_string(),
+            ..Default::default()
+        };
+        let prepared = shape
+            .prepare("https://example.com/hook", &BTreeMap::new(), &metadata(content.len()), &path)
+            .unwrap();
+        let text = String::from_utf8_lossy(&read_body(&prepared)).to_string();
+        let json_part = "Content-Disposition: form-data; name=\"metadata\"\r\nContent-Type: application/json\r\n\r\n";
+        let start = text.find(json_part).expect("metadata part") + json_part.len();
+        let end = start + text[start..].find("\r\n").unwrap();
+        let metadata: Value = serde_json::from_str(&text[start..end]).unwrap();
+        assert_eq!(metadata["filename"], "talk.ogg");
+        assert_eq!(metadata["markers"][0]["label"], "intro");
+        assert!(!text.contains("name=\"filename\""));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn prepare_base64_json_encodes_the_file() {
+        // Sizes around the encoder's chunk, with and without padding
+        for len in [0, 1, 2, 3, BASE64_CHUNK - 1, BASE64_CHUNK, BASE64_CHUNK + 1, 3 * BASE64_CHUNK + 2] {
+            let (path, content) = recording(&format!("base64-{}", len), len);
+            let shape = RequestShape {
+                body: BodyMode::Base64Json,
+                ..Default::default()
+            };
+            let prepared = shape
+                .prepare("https://example.com/hook", &BTreeMap::new(), &metadata(len), &path)
+                .unwrap();
+            assert_eq!(header(&prepared, "Content-Type"), Some("application/json"));
+            let json: Value = serde_json::from_slice(&read_body(&prepared)).unwrap();
+            assert_eq!(json["file"], base64::engine::general_purpose::STANDARD.encode(&content), "{} bytes", len);
+            assert_eq!(json["max_speakers"], 3);
+            assert_eq!(json["markers"][0]["time"], 1.5);
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn prepare_body_can_be_read_again() {
+        let (path, content) = recording("reread", 300);
+        let prepared = RequestShape::default()
+            .prepare("https://example.com/hook", &BTreeMap::new(), &metadata(content.len()), &path)
+            .unwrap();
+        assert_eq!(read_body(&prepared), read_body(&prepared));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    fn head_value<'a>(head: &'a [String], name: &str) -> Option<&'a str> {
+        head.iter().find_map(|line| {
+            let (header, value) = line.split_once(':')?;
+            header.eq_ignore_ascii_case(name).then(|| value.trim())
+        })
+    }
+
+    /// Accept one request, answer it with `response`, and return its head and body
+    fn serve_once(listener: TcpListener, response: &'static str) -> std::thread::JoinHandle<(Vec<String>, Vec<u8>)> {
+        std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut head = Vec::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.trim_end().to_string();
+                if line.is_empty() {
+                    break;
+                }
+                head.push(line);
+            }
+            let length: usize = head_value(&head, "Content-Length")
+                .and_then(|value| value.parse().ok())
+                .expect("Content-Length");
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            write!(
+                &stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                response.len(),
+                response
+            )
+            .unwrap();
+            (head, body)
+        })
+    }
+
+    #[test]
+    fn webhook_upload_posts_a_multipart_form() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = serve_once(listener, r#"{"data":{"url":"https://example.com/t/1","id":7}}"#);
+
+        let (path, content) = recording("webhook", 70_000);
+        let destination = crate::UploadDestination {
+            name: "Transcription".to_string(),
+            url: format!("http://127.0.0.1:{}/hook/{{stem}}", port),
+            request: RequestShape {
+                response: ResponseMapping {
+                    url: "data.url".to_string(),
+                    id: "/data/id".to_string(),
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let response = crate::upload_to_n8n_sync(path.to_str().unwrap(), &destination, 1, 4).unwrap();
+        let (head, body) = server.join().unwrap();
+
+        let file_stem = path.file_stem().unwrap().to_str().unwrap();
+        assert_eq!(head[0], format!("POST /hook/{} HTTP/1.1", file_stem));
+        let content_type = head_value(&head, "Content-Type").expect("Content-Type header");
+        let boundary = boundary(content_type);
+        assert!(boundary.starts_with("audio-recorder-"));
+        assert!(body.starts_with(format!("--{}\r\n", boundary).as_bytes()));
+        assert!(body.ends_with(format!("\r\n--{}--\r\n", boundary).as_bytes()));
+        assert!(body.windows(content.len()).any(|window| window == content.as_slice()));
+        let text = String::from_utf8_lossy(&body);
+        assert!(text.contains("name=\"max_speakers\"\r\n\r\n4\r\n"));
+
+        assert_eq!(response.url.as_deref(), Some("https://example.com/t/1"));
+        assert_eq!(response.id.as_deref(), Some("7"));
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,268 @@
+// Upload of recordings to a WebDAV server (Nextcloud, ownCloud, ...). The destination
+// URL is the base collection (`https://<host>/remote.php/dav/files/<user>/` for
+// Nextcloud) and the folder template a path below it. The folders are created with
+// MKCOL before the file is sent, as a PUT into a missing folder fails only once the
+// whole file has been sent. Authentication is the destination's: HTTP Basic with an app password for
+// Nextcloud.
+
+use std::fs::File;
+
+use reqwest::blocking::{Client, Response};
+use reqwest::{Method, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use crate::upload_auth::{authorize, AuthMethod};
+use crate::upload_progress;
+use crate::upload_request::{check_template, expand, uri_encode, UploadMetadata};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebdavTarget {
+    /// Folder below the destination URL, a template like the upload URL; empty for the
+    /// URL itself
+    pub folder_template: String,
+}
+
+impl Default for WebdavTarget {
+    fn default() -> Self {
+        Self {
+            folder_template: "Recordings/{date}".to_string(),
+        }
+    }
+}
+
+impl WebdavTarget {
+    /// Check the target before saving it
+    pub fn validate(&self, url: &str) -> Result<(), String> {
+        if !(url.starts_with("http://") || url.starts_with("https://")) {
+            return Err(format!("URL must start with http:// or https:// ({})", url));
+        }
+        check_template(&self.folder_template)
+    }
+}
+
+/// PUT a recording in its folder, creating the folders missing; returns the file URL
+pub fn upload(
+    file_path: &str,
+    url: &str,
+    target: &WebdavTarget,
+    auth: &AuthMethod,
+    metadata: &UploadMetadata,
+) -> Result<String, String> {
+    let folder = expand(&target.folder_template, &metadata.variables())?;
+    let segments: Vec<&str> = folder.split('/').filter(|segment| !segment.is_empty()).collect();
+    // URL of the collection made of the first `depth` folder segments
+    let collection_url = |depth: usize| {
+        segments[..depth]
+            .iter()
+            .fold(url.trim_end_matches('/').to_string(), |parent, segment| {
+                format!("{}/{}", parent, uri_encode(segment, true))
+            })
+    };
+    let file_url = format!("{}/{}", collection_url(segments.len()), uri_encode(metadata.file_name, true));
+
+    let client = Client::new();
+    for depth in 1..=segments.len() {
+        make_collection(&client, &format!("{}/", collection_url(depth)), auth, metadata.destination)?;
+    }
+
+    println!("WebDAV upload: {} -> {}", file_path, file_url);
+    let response = send(&client, Method::PUT, &file_url, auth, metadata.destination, Some(file_path))?;
+    if response.status().is_success() {
+        Ok(file_url)
+    } else {
+        Err(format!("HTTP {}: {}", response.status(), response.text().unwrap_or_default()))
+    }
+}
+
+/// MKCOL a folder; an existing one is not an error
+fn make_collection(client: &Client, collection_url: &str, auth: &AuthMethod, destination: &str) -> Result<(), String> {
+    let mkcol = Method::from_bytes(b"MKCOL").expect("MKCOL is a valid method");
+    let response = send(client, mkcol, collection_url, auth, destination, None)?;
+    match response.status() {
+        status if status.is_success() => {
+            println!("WebDAV folder created: {}", collection_url);
+            Ok(())
+        }
+        // 405 Method Not Allowed: the collection already exists
+        StatusCode::METHOD_NOT_ALLOWED => Ok(()),
+        status => Err(format!("MKCOL {} failed: HTTP {}", collection_url, status)),
+    }
+}
+
+/// Send a request with the recording at `file_path` as body, or none
+fn send(
+    client: &Client,
+    method: Method,
+    target_url: &str,
+    auth: &AuthMethod,
+    destination: &str,
+    file_path: Option<&str>,
+) -> Result<Response, String> {
+    let open = |path: &str| File::open(path).map_err(|e| format!("cannot read {}: {}", path, e));
+    let len = match file_path {
+        Some(path) => open(path)?.metadata().map_err(|e| e.to_string())?.len(),
+        None => 0,
+    };
+    let mut request = client
+        .request(method, target_url)
+        .timeout(upload_progress::timeout_for(len));
+    let Some(path) = file_path else {
+        let request = authorize(request, auth, destination, &mut std::io::empty())?;
+        return request.send().map_err(|e| e.to_string());
+    };
+    request = request.header("Content-Type", "audio/ogg");
+    let request = authorize(request, auth, destination, &mut open(path)?)?;
+    request
+        .body(upload_progress::body(open(path)?, len))
+        .send()
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Local;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::path::PathBuf;
+
+    fn metadata(size: usize) -> UploadMetadata<'static> {
+        UploadMetadata {
+            file_name: "talk 1.ogg",
+            timestamp: Local::now(),
+            min_speakers: 2,
+            max_speakers: 3,
+            markers_json: None,
+            destination: "Nextcloud",
+            size,
+        }
+    }
+
+    /// Recording of `len` bytes in the temporary directory, named after the test
+    fn recording(name: &str, len: usize) -> (PathBuf, Vec<u8>) {
+        let content: Vec<u8> = (0..len).map(|i| (i * 31 % 256) as u8).collect();
+        let path = std::env::temp_dir().join(format!("audio-recorder-test-{}-{}.ogg", name, std::process::id()));
+        std::fs::write(&path, &content).unwrap();
+        (path, content)
+    }
+
+    /// Answer one request per status, each on its own connection, and return the
+    /// request lines and bodies
+    fn serve(listener: TcpListener, statuses: &'static [u16]) -> std::thread::JoinHandle<Vec<(String, Vec<u8>)>> {
+        std::thread::spawn(move || {
+            let mut requests = Vec::new();
+            for status in statuses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut head = Vec::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let line = line.trim_end().to_string();
+                    if line.is_empty() {
+                        break;
+                    }
+                    head.push(line);
+                }
+                // MKCOL has no body
+                let length: usize = head
+                    .iter()
+                    .find_map(|line| {
+                        let (header, value) = line.split_once(':')?;
+                        header.eq_ignore_ascii_case("Content-Length").then(|| value.trim().parse().ok())?
+                    })
+                    .unwrap_or(0);
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                write!(
+                    &stream,
+                    "HTTP/1.1 {} Status\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                )
+                .unwrap();
+                requests.push((head[0].clone(), body));
+            }
+            requests
+        })
+    }
+
+    #[test]
+    fn upload_creates_the_folders_then_puts_the_file() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        // The first folder exists already
+        let server = serve(listener, &[405, 201, 201]);
+
+        let (path, content) = recording("webdav", 70_000);
+        let target = WebdavTarget {
+            folder_template: "Recordings/{destination}".to_string(),
+        };
+        let url = format!("http://127.0.0.1:{}/dav/", port);
+        let file_url =
+            upload(path.to_str().unwrap(), &url, &target, &AuthMethod::None, &metadata(content.len())).unwrap();
+        let requests = server.join().unwrap();
+
+        let lines: Vec<&str> = requests.iter().map(|(line, _)| line.as_str()).collect();
+        assert_eq!(
+            lines,
+            [
+                "MKCOL /dav/Recordings/ HTTP/1.1",
+                "MKCOL /dav/Recordings/Nextcloud/ HTTP/1.1",
+                "PUT /dav/Recordings/Nextcloud/talk%201.ogg HTTP/1.1",
+            ]
+        );
+        assert_eq!(requests[2].1, content);
+        assert_eq!(file_url, format!("http://127.0.0.1:{}/dav/Recordings/Nextcloud/talk%201.ogg", port));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn upload_encodes_each_folder_segment() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = serve(listener, &[405, 405, 405, 201]);
+
+        let (path, content) = recording("webdav-encoding", 10);
+        // Empty segments are skipped; spaces, accents and '?' are encoded in each segment
+        let target = WebdavTarget {
+            folder_template: "/Réunions//Équipe A/Q&A?/".to_string(),
+        };
+        let url = format!("http://127.0.0.1:{}/remote.php/dav/files/me", port);
+        upload(path.to_str().unwrap(), &url, &target, &AuthMethod::None, &metadata(content.len())).unwrap();
+        let requests = server.join().unwrap();
+
+        let lines: Vec<&str> = requests.iter().map(|(line, _)| line.as_str()).collect();
+        assert_eq!(
+            lines,
+            [
+                "MKCOL /remote.php/dav/files/me/R%C3%A9unions/ HTTP/1.1",
+                "MKCOL /remote.php/dav/files/me/R%C3%A9unions/%C3%89quipe%20A/ HTTP/1.1",
+                "MKCOL /remote.php/dav/files/me/R%C3%A9unions/%C3%89quipe%20A/Q%26A%3F/ HTTP/1.1",
+                "PUT /remote.php/dav/files/me/R%C3%A9unions/%C3%89quipe%20A/Q%26A%3F/talk%201.ogg HTTP/1.1",
+            ]
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn upload_stops_when_a_folder_cannot_be_created() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = serve(listener, &[403]);
+
+        let (path, content) = recording("webdav-forbidden", 10);
+        let target = WebdavTarget {
+            folder_template: "Recordings/{date}".to_string(),
+        };
+        let url = format!("http://127.0.0.1:{}/dav", port);
+        let error =
+            upload(path.to_str().unwrap(), &url, &target, &AuthMethod::None, &metadata(content.len())).unwrap_err();
+        let requests = server.join().unwrap();
+
+        assert_eq!(requests.len(), 1);
+        assert!(error.starts_with("MKCOL "), "{}", error);
+        assert!(error.ends_with("HTTP 403 Forbidden"), "{}", error);
+        std::fs::remove_file(path).unwrap();
+    }
+}